@@ -1,13 +1,17 @@
 use matching_engine::{
-    AmendOrder, AssetPair, CancelOrder, LimitOrder, LimitOrderRepositoryLike, MarketOrder,
-    MatchingEngine, OrderRequest, OrderSide, OrderType,
+    Allocation, AmendOrder, AssetPair, CancelOrder, ChildOrderRepositoryLike, ChildOrders,
+    ExecutionConstraint, Iceberg, LimitOrder, LimitOrderRepositoryLike, MarketOrder,
+    MarketOrderQuantity, MatchingEngine, Notional, OrderLink, OrderLinkRepositoryLike,
+    OrderRequest, OrderSide, OrderType, ParticipantRole, Peg, PegType, PostOnly, PriceProtection,
+    SessionState, StopOrder, StopOrderRepositoryLike, TimeInForce, TradingScheduleLike,
+    TrailingOffset, TrailingStop,
 };
 use redb::ReadableTable;
 use rust_decimal::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::fmt::Debug;
-use std::ops::Sub;
+use std::ops::{Add, Div, Mul, Sub};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Debug)]
@@ -57,7 +61,7 @@ type MyOrderId = ulid::Ulid;
 
 const MAX_PRICE_PRECISION: u32 = 18;
 
-#[derive(Debug, Copy, Clone, Eq, Deserialize, Serialize)]
+#[derive(Debug, Default, Copy, Clone, Eq, Deserialize, Serialize)]
 pub struct MyPrice {
     pub num: u64,
     pub scale: u32,
@@ -81,6 +85,18 @@ impl MyPrice {
     pub fn decimal(&self) -> Decimal {
         Decimal::new(self.num as i64, self.scale)
     }
+
+    pub fn get_num_by_scale(&self, scale: u32) -> u64 {
+        if scale == self.scale {
+            self.num
+        } else {
+            if self.scale < scale {
+                self.num * 10_u64.pow(scale - self.scale)
+            } else {
+                self.num / 10_u64.pow(self.scale - scale)
+            }
+        }
+    }
 }
 
 impl PartialEq for MyPrice {
@@ -113,13 +129,55 @@ impl PartialOrd for MyPrice {
     }
 }
 
+impl Add for MyPrice {
+    type Output = MyPrice;
+    fn add(self, other: Self) -> Self {
+        let scale = self.scale.max(other.scale);
+        Self {
+            num: self.get_num_by_scale(scale) + other.get_num_by_scale(scale),
+            scale,
+        }
+    }
+}
+
+impl Sub for MyPrice {
+    type Output = MyPrice;
+    fn sub(self, other: Self) -> Self {
+        let scale = self.scale.max(other.scale);
+        Self {
+            num: self.get_num_by_scale(scale) - other.get_num_by_scale(scale),
+            scale,
+        }
+    }
+}
+
+impl Mul<u64> for MyPrice {
+    type Output = MyPrice;
+    fn mul(self, other: u64) -> Self {
+        Self {
+            num: self.num * other,
+            scale: self.scale,
+        }
+    }
+}
+
+impl Div<u64> for MyPrice {
+    type Output = MyPrice;
+    fn div(self, other: u64) -> Self {
+        Self {
+            num: self.num / other,
+            scale: self.scale,
+        }
+    }
+}
+
 impl core::fmt::Display for MyPrice {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "{}", self.decimal())
     }
 }
 
-#[derive(Debug, Copy, Clone, Eq, Deserialize, Serialize)]
+#[derive(Debug, Default, Copy, Clone, Eq, Deserialize, Serialize)]
 pub struct MyQuantity {
     pub num: u64,
     pub scale: u32,
@@ -183,6 +241,17 @@ impl PartialOrd for MyQuantity {
     }
 }
 
+impl Add for MyQuantity {
+    type Output = MyQuantity;
+    fn add(self, other: Self) -> Self {
+        let scale = self.scale.max(other.scale);
+        Self {
+            num: self.get_num_by_scale(scale) + other.get_num_by_scale(scale),
+            scale,
+        }
+    }
+}
+
 impl Sub for MyQuantity {
     type Output = MyQuantity;
     fn sub(self, other: Self) -> Self {
@@ -209,6 +278,48 @@ impl Sub for MyQuantity {
     }
 }
 
+/// base lot of the quantity (0.0001)
+const QUANTITY_LOT_SCALE: u32 = 4;
+
+impl Notional<MyPrice> for MyQuantity {
+    fn notional(&self, price: MyPrice) -> Self {
+        Self {
+            num: self.num.saturating_mul(price.num),
+            scale: self.scale + price.scale,
+        }
+    }
+
+    fn from_notional(quote_quantity: Self, price: MyPrice) -> Option<Self> {
+        let quantity = quote_quantity
+            .decimal()
+            .checked_div(price.decimal())?
+            .round_dp_with_strategy(QUANTITY_LOT_SCALE, RoundingStrategy::ToZero);
+        if quantity.is_zero() {
+            None
+        } else {
+            Some(Self::new(quantity.mantissa() as u64, quantity.scale()))
+        }
+    }
+}
+
+impl Allocation for MyQuantity {
+    fn allocation(&self, weight: Self, total_weight: Self, lot_size: Self) -> Option<Self> {
+        let lots =
+            (self.decimal() * weight.decimal() / total_weight.decimal() / lot_size.decimal())
+                .floor();
+        let allocation = lots * lot_size.decimal();
+        if allocation.is_zero() {
+            None
+        } else {
+            Some(Self::new(allocation.mantissa() as u64, allocation.scale()))
+        }
+    }
+
+    fn fraction(&self, basis_points: u64, lot_size: Self) -> Option<Self> {
+        self.allocation(Self::new(basis_points, 0), Self::new(10_000, 0), lot_size)
+    }
+}
+
 impl core::fmt::Display for MyQuantity {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "{}", self.decimal())
@@ -219,25 +330,47 @@ const BID_LIMIT_ORDER_PRICE_INDEX: redb::TableDefinition<u128, &[u8]> =
     redb::TableDefinition::new("bid_limit_order_prices");
 const ASK_LIMIT_ORDER_PRICE_INDEX: redb::TableDefinition<u128, &[u8]> =
     redb::TableDefinition::new("ask_limit_order_prices");
+const STOP_ORDER_RISE_PRICE_INDEX: redb::TableDefinition<u128, &[u8]> =
+    redb::TableDefinition::new("stop_order_rise_prices");
+const STOP_ORDER_FALL_PRICE_INDEX: redb::TableDefinition<u128, &[u8]> =
+    redb::TableDefinition::new("stop_order_fall_prices");
 const BID_LIMIT_ORDER_TABLE: redb::TableDefinition<u128, &[u8]> =
     redb::TableDefinition::new("bid_limit_orders");
 const ASK_LIMIT_ORDER_TABLE: redb::TableDefinition<u128, &[u8]> =
     redb::TableDefinition::new("ask_limit_orders");
-
+const STOP_ORDER_TABLE: redb::TableDefinition<u128, &[u8]> =
+    redb::TableDefinition::new("stop_orders");
+const ORDER_LINK_TABLE: redb::TableDefinition<u128, &[u8]> =
+    redb::TableDefinition::new("order_links");
+const CHILD_ORDER_TABLE: redb::TableDefinition<u128, &[u8]> =
+    redb::TableDefinition::new("child_orders");
+
+/// ids of the orders at a price level. hidden limit orders rank behind the displayed ones.
 #[derive(Deserialize, Serialize, Default)]
-struct PriceIndexValue(Vec<u128>);
+struct PriceIndexValue {
+    ids: Vec<u128>,
+    hidden_ids: Vec<u128>,
+}
 
 impl PriceIndexValue {
     pub fn is_empty(&self) -> bool {
-        self.0.is_empty()
+        self.ids.is_empty() && self.hidden_ids.is_empty()
     }
     pub fn push(&mut self, id: &MyOrderId) {
-        self.0.push(id.0)
+        self.ids.push(id.0)
+    }
+    pub fn push_hidden(&mut self, id: &MyOrderId) {
+        self.hidden_ids.push(id.0)
     }
     pub fn remove(&mut self, id: &MyOrderId) {
-        if let Some((index, _)) = self.0.iter().enumerate().find(|(_, i)| **i == id.0) {
-            self.0.remove(index);
-        }
+        self.ids.retain(|i| *i != id.0);
+        self.hidden_ids.retain(|i| *i != id.0);
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &u128> {
+        self.ids.iter().chain(self.hidden_ids.iter())
+    }
+    pub fn first(&self) -> Option<&u128> {
+        self.iter().next()
     }
     pub fn encode<W: std::io::Write>(&self, w: W) -> Result<()> {
         ciborium::ser::into_writer(&self, w).map_err(Into::into)
@@ -249,12 +382,292 @@ impl PriceIndexValue {
 
 #[derive(Deserialize, Serialize)]
 struct LimitOrderValue {
+    pub owner: Option<u64>,
     pub price: MyPrice,
     pub quantity: MyQuantity,
+    pub original_quantity: MyQuantity,
+    pub filled_quantity: Option<MyQuantity>,
+    pub market_to_limit: bool,
+    /// `None` for plain limit orders, `Some(true)` if a crossing post-only order is repriced
+    pub post_only_reprice: Option<bool>,
+    pub peak_quantity: Option<MyQuantity>,
+    pub hidden_quantity: Option<MyQuantity>,
+    pub hidden: bool,
+    pub lead_market_maker: bool,
+    pub top_order: bool,
+    pub expire_time_ms: Option<u64>,
+    /// 0: primary, 1: market, 2: midpoint
+    pub peg_type: Option<u8>,
+    pub peg_offset: Option<MyPrice>,
+    pub peg_price_cap: Option<MyPrice>,
     pub timestamp_ms: u64,
 }
 
 impl LimitOrderValue {
+    pub fn from_order(order: &LimitOrder<MyOrderId, MyAsset, MyPrice, MyQuantity>) -> Self {
+        Self {
+            owner: order.owner,
+            price: order.price,
+            quantity: order.quantity,
+            original_quantity: order.original_quantity,
+            filled_quantity: order.filled_quantity,
+            market_to_limit: order.market_to_limit,
+            post_only_reprice: order
+                .post_only
+                .map(|post_only| post_only == PostOnly::Reprice),
+            peak_quantity: order.iceberg.map(|iceberg| iceberg.peak_quantity),
+            hidden_quantity: order.iceberg.and_then(|iceberg| iceberg.hidden_quantity),
+            hidden: order.hidden,
+            lead_market_maker: order.participant_role == ParticipantRole::LeadMarketMaker,
+            top_order: order.top_order,
+            expire_time_ms: order.expire_time_ms,
+            peg_type: order.peg.map(|peg| match peg.peg_type {
+                PegType::Primary => 0,
+                PegType::Market => 1,
+                PegType::Midpoint => 2,
+            }),
+            peg_offset: order.peg.and_then(|peg| peg.offset),
+            peg_price_cap: order.peg.and_then(|peg| peg.price_cap),
+            timestamp_ms: order.timestamp_ms,
+        }
+    }
+    pub fn into_order(
+        self,
+        id: MyOrderId,
+        asset_pair: MyAssetPair,
+        side: OrderSide,
+    ) -> LimitOrder<MyOrderId, MyAsset, MyPrice, MyQuantity> {
+        LimitOrder {
+            id,
+            asset_pair,
+            side,
+            owner: self.owner,
+            price: self.price,
+            quantity: self.quantity,
+            original_quantity: self.original_quantity,
+            filled_quantity: self.filled_quantity,
+            // only GTC orders rest in the book. DAY orders rest with the session close as
+            // their expire time.
+            time_in_force: TimeInForce::GTC,
+            market_to_limit: self.market_to_limit,
+            post_only: self.post_only_reprice.map(|reprice| {
+                if reprice {
+                    PostOnly::Reprice
+                } else {
+                    PostOnly::Reject
+                }
+            }),
+            iceberg: self.peak_quantity.map(|peak_quantity| Iceberg {
+                peak_quantity,
+                hidden_quantity: self.hidden_quantity,
+            }),
+            hidden: self.hidden,
+            participant_role: if self.lead_market_maker {
+                ParticipantRole::LeadMarketMaker
+            } else {
+                ParticipantRole::Regular
+            },
+            top_order: self.top_order,
+            expire_time_ms: self.expire_time_ms,
+            peg: self.peg_type.map(|peg_type| Peg {
+                peg_type: match peg_type {
+                    0 => PegType::Primary,
+                    1 => PegType::Market,
+                    _ => PegType::Midpoint,
+                },
+                offset: self.peg_offset,
+                price_cap: self.peg_price_cap,
+            }),
+            // constraints only apply to the incoming order
+            execution_constraint: None,
+            timestamp_ms: self.timestamp_ms,
+        }
+    }
+    pub fn encode<W: std::io::Write>(&self, w: W) -> Result<()> {
+        ciborium::ser::into_writer(&self, w).map_err(Into::into)
+    }
+    pub fn decode(slice: &[u8]) -> Result<Self> {
+        ciborium::de::from_reader(slice).map_err(Into::into)
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct StopOrderValue {
+    pub bid: bool,
+    pub owner: Option<u64>,
+    pub take_profit: bool,
+    pub stop_price: MyPrice,
+    pub price: Option<MyPrice>,
+    pub quantity: MyQuantity,
+    pub trailing_offset: Option<MyPrice>,
+    pub trailing_offset_bps: Option<u64>,
+    pub water_mark: Option<MyPrice>,
+    pub timestamp_ms: u64,
+}
+
+impl StopOrderValue {
+    pub fn from_order(order: &StopOrder<MyOrderId, MyAsset, MyPrice, MyQuantity>) -> Self {
+        Self {
+            bid: matches!(order.side, OrderSide::Bid),
+            owner: order.owner,
+            take_profit: matches!(
+                order.order_type,
+                OrderType::TakeProfit | OrderType::TakeProfitLimit
+            ),
+            stop_price: order.stop_price,
+            price: order.price,
+            quantity: order.quantity,
+            trailing_offset: order.trailing_stop.and_then(|t| match t.offset {
+                TrailingOffset::Absolute(offset) => Some(offset),
+                TrailingOffset::BasisPoints(_) => None,
+            }),
+            trailing_offset_bps: order.trailing_stop.and_then(|t| match t.offset {
+                TrailingOffset::Absolute(_) => None,
+                TrailingOffset::BasisPoints(bps) => Some(bps),
+            }),
+            water_mark: order.trailing_stop.and_then(|t| t.water_mark),
+            timestamp_ms: order.timestamp_ms,
+        }
+    }
+    pub fn into_order(
+        self,
+        id: MyOrderId,
+        asset_pair: MyAssetPair,
+    ) -> StopOrder<MyOrderId, MyAsset, MyPrice, MyQuantity> {
+        let trailing_offset = match (self.trailing_offset, self.trailing_offset_bps) {
+            (Some(offset), _) => Some(TrailingOffset::Absolute(offset)),
+            (None, Some(bps)) => Some(TrailingOffset::BasisPoints(bps)),
+            (None, None) => None,
+        };
+        StopOrder {
+            id,
+            asset_pair,
+            order_type: match (self.take_profit, self.price.is_some()) {
+                (false, false) if trailing_offset.is_some() => OrderType::TrailingStop,
+                (false, false) => OrderType::StopLoss,
+                (false, true) => OrderType::StopLossLimit,
+                (true, false) => OrderType::TakeProfit,
+                (true, true) => OrderType::TakeProfitLimit,
+            },
+            side: if self.bid {
+                OrderSide::Bid
+            } else {
+                OrderSide::Ask
+            },
+            owner: self.owner,
+            stop_price: self.stop_price,
+            price: self.price,
+            quantity: self.quantity,
+            trailing_stop: trailing_offset.map(|offset| TrailingStop {
+                offset,
+                water_mark: self.water_mark,
+            }),
+            timestamp_ms: self.timestamp_ms,
+        }
+    }
+    pub fn encode<W: std::io::Write>(&self, w: W) -> Result<()> {
+        ciborium::ser::into_writer(&self, w).map_err(Into::into)
+    }
+    pub fn decode(slice: &[u8]) -> Result<Self> {
+        ciborium::de::from_reader(slice).map_err(Into::into)
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct OrderLinkValue {
+    pub linked_order_id: u128,
+    pub linked_order_type: u8,
+    pub linked_bid: bool,
+}
+
+impl OrderLinkValue {
+    pub fn from_link(link: &OrderLink<MyOrderId>) -> Self {
+        Self {
+            linked_order_id: link.linked_order_id.0,
+            linked_order_type: match link.linked_order_type {
+                OrderType::Market => 0,
+                OrderType::Limit => 1,
+                OrderType::LimitMaker => 2,
+                OrderType::StopLoss => 3,
+                OrderType::StopLossLimit => 4,
+                OrderType::TakeProfit => 5,
+                OrderType::TakeProfitLimit => 6,
+                OrderType::TrailingStop => 7,
+                OrderType::MarketToLimit => 8,
+            },
+            linked_bid: matches!(link.linked_side, OrderSide::Bid),
+        }
+    }
+    pub fn into_link(self, order_id: MyOrderId) -> OrderLink<MyOrderId> {
+        OrderLink {
+            order_id,
+            linked_order_id: MyOrderId::from(self.linked_order_id),
+            linked_order_type: match self.linked_order_type {
+                0 => OrderType::Market,
+                1 => OrderType::Limit,
+                2 => OrderType::LimitMaker,
+                3 => OrderType::StopLoss,
+                4 => OrderType::StopLossLimit,
+                5 => OrderType::TakeProfit,
+                6 => OrderType::TakeProfitLimit,
+                7 => OrderType::TrailingStop,
+                _ => OrderType::MarketToLimit,
+            },
+            linked_side: if self.linked_bid {
+                OrderSide::Bid
+            } else {
+                OrderSide::Ask
+            },
+        }
+    }
+    pub fn encode<W: std::io::Write>(&self, w: W) -> Result<()> {
+        ciborium::ser::into_writer(&self, w).map_err(Into::into)
+    }
+    pub fn decode(slice: &[u8]) -> Result<Self> {
+        ciborium::de::from_reader(slice).map_err(Into::into)
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct ChildOrdersValue {
+    pub take_profit_id: u128,
+    pub take_profit: LimitOrderValue,
+    pub stop_loss_id: u128,
+    pub stop_loss: StopOrderValue,
+    pub filled_quantity: Option<MyQuantity>,
+}
+
+impl ChildOrdersValue {
+    pub fn from_child_orders(
+        child_orders: &ChildOrders<MyOrderId, MyAsset, MyPrice, MyQuantity>,
+    ) -> Self {
+        Self {
+            take_profit_id: child_orders.take_profit_order.id.0,
+            take_profit: LimitOrderValue::from_order(&child_orders.take_profit_order),
+            stop_loss_id: child_orders.stop_loss_order.id.0,
+            stop_loss: StopOrderValue::from_order(&child_orders.stop_loss_order),
+            filled_quantity: child_orders.filled_quantity,
+        }
+    }
+    pub fn into_child_orders(
+        self,
+        parent_id: MyOrderId,
+        asset_pair: MyAssetPair,
+    ) -> ChildOrders<MyOrderId, MyAsset, MyPrice, MyQuantity> {
+        let stop_loss_order = self
+            .stop_loss
+            .into_order(MyOrderId::from(self.stop_loss_id), asset_pair.clone());
+        ChildOrders {
+            parent_id,
+            take_profit_order: self.take_profit.into_order(
+                MyOrderId::from(self.take_profit_id),
+                asset_pair,
+                stop_loss_order.side,
+            ),
+            stop_loss_order,
+            filled_quantity: self.filled_quantity,
+        }
+    }
     pub fn encode<W: std::io::Write>(&self, w: W) -> Result<()> {
         ciborium::ser::into_writer(&self, w).map_err(Into::into)
     }
@@ -282,11 +695,7 @@ impl<'db> MyBidLimitOrderRepository<'db> {
         order_table: &mut redb::Table<'db, 'txn, u128, &[u8]>,
         order: &LimitOrder<MyOrderId, MyAsset, MyPrice, MyQuantity>,
     ) -> Result<()> {
-        let value = LimitOrderValue {
-            price: order.price,
-            quantity: order.quantity,
-            timestamp_ms: order.timestamp_ms,
-        };
+        let value = LimitOrderValue::from_order(order);
         let mut bytes = Vec::new();
         value.encode(&mut bytes)?;
         order_table.insert(&order.id.0, &bytes)?;
@@ -298,6 +707,7 @@ impl<'db> MyBidLimitOrderRepository<'db> {
         index: &mut redb::Table<'db, 'txn, u128, &[u8]>,
         price: &MyPrice,
         order_id: &MyOrderId,
+        hidden: bool,
     ) -> Result<()> {
         let key: u128 = (*price).into();
         let mut value = if let Some(ids) = index.get(&key)? {
@@ -305,7 +715,11 @@ impl<'db> MyBidLimitOrderRepository<'db> {
         } else {
             PriceIndexValue::default()
         };
-        value.push(&order_id);
+        if hidden {
+            value.push_hidden(&order_id);
+        } else {
+            value.push(&order_id);
+        }
         let mut index_value_bytes = Vec::new();
         value.encode(&mut index_value_bytes)?;
         index.insert(&key, &index_value_bytes)?;
@@ -357,7 +771,7 @@ impl<'db> LimitOrderRepositoryLike for MyBidLimitOrderRepository<'db> {
         // update index
         {
             let mut price_index = tx.open_table(BID_LIMIT_ORDER_PRICE_INDEX)?;
-            self.add_index_value(&mut price_index, &order.price, &order.id)?;
+            self.add_index_value(&mut price_index, &order.price, &order.id, order.hidden)?;
         }
         Ok(())
     }
@@ -368,18 +782,21 @@ impl<'db> LimitOrderRepositoryLike for MyBidLimitOrderRepository<'db> {
         order: &LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
     ) -> std::result::Result<(), Self::Err> {
         if let Some(old_order) = self.get_by_order_id(tx, &order.id)? {
-            if old_order.price != order.price {
+            if old_order.price != order.price
+                || old_order.timestamp_ms != order.timestamp_ms
+                || old_order.hidden != order.hidden
+            {
                 // delete & update index
                 let mut price_index = tx.open_table(BID_LIMIT_ORDER_PRICE_INDEX)?;
                 self.delete_index_value(&mut price_index, &old_order.price, &order.id)?;
-                self.add_index_value(&mut price_index, &order.price, &order.id)?;
+                self.add_index_value(&mut price_index, &order.price, &order.id, order.hidden)?;
             } else {
                 // no need to update index
             }
         } else {
             // update index
             let mut price_index = tx.open_table(BID_LIMIT_ORDER_PRICE_INDEX)?;
-            self.add_index_value(&mut price_index, &order.price, &order.id)?;
+            self.add_index_value(&mut price_index, &order.price, &order.id, order.hidden)?;
         }
         // update or insert order
         {
@@ -422,14 +839,11 @@ impl<'db> LimitOrderRepositoryLike for MyBidLimitOrderRepository<'db> {
             .get(&order_id.0)?
             .expect("indexed key missing its reference...");
         let order = LimitOrderValue::decode(order_bytes.value())?;
-        Ok(Some(LimitOrder {
-            id: order_id.clone(),
-            asset_pair: self.asset_pair.clone(),
-            side: OrderSide::Bid,
-            price: order.price,
-            quantity: order.quantity,
-            timestamp_ms: order.timestamp_ms,
-        }))
+        Ok(Some(order.into_order(
+            order_id.clone(),
+            self.asset_pair.clone(),
+            OrderSide::Bid,
+        )))
     }
 
     fn next(
@@ -443,9 +857,10 @@ impl<'db> LimitOrderRepositoryLike for MyBidLimitOrderRepository<'db> {
             let price_index = tx.open_table(BID_LIMIT_ORDER_PRICE_INDEX)?;
             let mut iter = price_index.iter()?;
             let id = {
-                if let Some((_, id_array_bytes)) = iter.next() {
+                // the highest price first
+                if let Some((_, id_array_bytes)) = iter.next_back() {
                     let ids = PriceIndexValue::decode(id_array_bytes.value())?;
-                    let id = ids.0.first().expect("index node must have at least one id");
+                    let id = ids.first().expect("index node must have at least one id");
                     let id = MyOrderId::from(*id);
                     Some(id)
                 } else {
@@ -460,6 +875,73 @@ impl<'db> LimitOrderRepositoryLike for MyBidLimitOrderRepository<'db> {
             Ok(None)
         }
     }
+
+    fn next_after(
+        &self,
+        tx: &mut Self::Transaction,
+        order: &LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+    ) -> std::result::Result<
+        Option<LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>>,
+        Self::Err,
+    > {
+        let id = {
+            let price_index = tx.open_table(BID_LIMIT_ORDER_PRICE_INDEX)?;
+            let key: u128 = order.price.into();
+            let same_price_id = {
+                if let Some(id_array_bytes) = price_index.get(&key)? {
+                    let ids = PriceIndexValue::decode(id_array_bytes.value())?;
+                    let id = ids
+                        .iter()
+                        .skip_while(|id| **id != order.id.0)
+                        .nth(1)
+                        .map(|id| MyOrderId::from(*id));
+                    id
+                } else {
+                    None
+                }
+            };
+            if same_price_id.is_some() {
+                same_price_id
+            } else {
+                // first order of the next lower price level
+                let mut iter = price_index.range(..key)?;
+                if let Some((_, id_array_bytes)) = iter.next_back() {
+                    let ids = PriceIndexValue::decode(id_array_bytes.value())?;
+                    let id = ids.first().expect("index node must have at least one id");
+                    Some(MyOrderId::from(*id))
+                } else {
+                    None
+                }
+            }
+        };
+        if let Some(id) = id {
+            self.get_by_order_id(tx, &id)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn get_pegged_orders(
+        &self,
+        tx: &mut Self::Transaction,
+    ) -> std::result::Result<
+        Vec<LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>>,
+        Self::Err,
+    > {
+        let order_table = tx.open_table(BID_LIMIT_ORDER_TABLE)?;
+        let mut orders = Vec::new();
+        for (id, order_bytes) in order_table.iter()? {
+            let order = LimitOrderValue::decode(order_bytes.value())?;
+            if order.peg_type.is_some() {
+                orders.push(order.into_order(
+                    MyOrderId::from(id.value()),
+                    self.asset_pair.clone(),
+                    OrderSide::Bid,
+                ));
+            }
+        }
+        Ok(orders)
+    }
 }
 
 pub struct MyAskLimitOrderRepository<'db> {
@@ -481,11 +963,7 @@ impl<'db> MyAskLimitOrderRepository<'db> {
         order_table: &mut redb::Table<'db, 'txn, u128, &[u8]>,
         order: &LimitOrder<MyOrderId, MyAsset, MyPrice, MyQuantity>,
     ) -> Result<()> {
-        let value = LimitOrderValue {
-            price: order.price,
-            quantity: order.quantity,
-            timestamp_ms: order.timestamp_ms,
-        };
+        let value = LimitOrderValue::from_order(order);
         let mut bytes = Vec::new();
         value.encode(&mut bytes)?;
         order_table.insert(&order.id.0, &bytes)?;
@@ -497,6 +975,7 @@ impl<'db> MyAskLimitOrderRepository<'db> {
         index: &mut redb::Table<'db, 'txn, u128, &[u8]>,
         price: &MyPrice,
         order_id: &MyOrderId,
+        hidden: bool,
     ) -> Result<()> {
         let key: u128 = (*price).into();
         let mut value = if let Some(ids) = index.get(&key)? {
@@ -504,7 +983,11 @@ impl<'db> MyAskLimitOrderRepository<'db> {
         } else {
             PriceIndexValue::default()
         };
-        value.push(&order_id);
+        if hidden {
+            value.push_hidden(&order_id);
+        } else {
+            value.push(&order_id);
+        }
         let mut index_value_bytes = Vec::new();
         value.encode(&mut index_value_bytes)?;
         index.insert(&key, &index_value_bytes)?;
@@ -556,7 +1039,7 @@ impl<'db> LimitOrderRepositoryLike for MyAskLimitOrderRepository<'db> {
         // update index
         {
             let mut price_index = tx.open_table(ASK_LIMIT_ORDER_PRICE_INDEX)?;
-            self.add_index_value(&mut price_index, &order.price, &order.id)?;
+            self.add_index_value(&mut price_index, &order.price, &order.id, order.hidden)?;
         }
         Ok(())
     }
@@ -567,18 +1050,21 @@ impl<'db> LimitOrderRepositoryLike for MyAskLimitOrderRepository<'db> {
         order: &LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
     ) -> std::result::Result<(), Self::Err> {
         if let Some(old_order) = self.get_by_order_id(tx, &order.id)? {
-            if old_order.price != order.price {
+            if old_order.price != order.price
+                || old_order.timestamp_ms != order.timestamp_ms
+                || old_order.hidden != order.hidden
+            {
                 // delete & update index
                 let mut price_index = tx.open_table(ASK_LIMIT_ORDER_PRICE_INDEX)?;
                 self.delete_index_value(&mut price_index, &old_order.price, &order.id)?;
-                self.add_index_value(&mut price_index, &order.price, &order.id)?;
+                self.add_index_value(&mut price_index, &order.price, &order.id, order.hidden)?;
             } else {
                 // no need to update index
             }
         } else {
             // update index
             let mut price_index = tx.open_table(ASK_LIMIT_ORDER_PRICE_INDEX)?;
-            self.add_index_value(&mut price_index, &order.price, &order.id)?;
+            self.add_index_value(&mut price_index, &order.price, &order.id, order.hidden)?;
         }
         // update or insert order
         {
@@ -621,14 +1107,11 @@ impl<'db> LimitOrderRepositoryLike for MyAskLimitOrderRepository<'db> {
             .get(&order_id.0)?
             .expect("indexed key missing its reference...");
         let order = LimitOrderValue::decode(order_bytes.value())?;
-        Ok(Some(LimitOrder {
-            id: order_id.clone(),
-            asset_pair: self.asset_pair.clone(),
-            side: OrderSide::Ask,
-            price: order.price,
-            quantity: order.quantity,
-            timestamp_ms: order.timestamp_ms,
-        }))
+        Ok(Some(order.into_order(
+            order_id.clone(),
+            self.asset_pair.clone(),
+            OrderSide::Ask,
+        )))
     }
 
     fn next(
@@ -642,9 +1125,10 @@ impl<'db> LimitOrderRepositoryLike for MyAskLimitOrderRepository<'db> {
             let price_index = tx.open_table(ASK_LIMIT_ORDER_PRICE_INDEX)?;
             let mut iter = price_index.iter()?;
             let id = {
-                if let Some((_, id_array_bytes)) = iter.next_back() {
+                // the lowest price first
+                if let Some((_, id_array_bytes)) = iter.next() {
                     let ids = PriceIndexValue::decode(id_array_bytes.value())?;
-                    let id = ids.0.first().expect("index node must have at least one id");
+                    let id = ids.first().expect("index node must have at least one id");
                     let id = MyOrderId::from(*id);
                     Some(id)
                 } else {
@@ -659,30 +1143,495 @@ impl<'db> LimitOrderRepositoryLike for MyAskLimitOrderRepository<'db> {
             Ok(None)
         }
     }
+
+    fn next_after(
+        &self,
+        tx: &mut Self::Transaction,
+        order: &LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+    ) -> std::result::Result<
+        Option<LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>>,
+        Self::Err,
+    > {
+        let id = {
+            let price_index = tx.open_table(ASK_LIMIT_ORDER_PRICE_INDEX)?;
+            let key: u128 = order.price.into();
+            let same_price_id = {
+                if let Some(id_array_bytes) = price_index.get(&key)? {
+                    let ids = PriceIndexValue::decode(id_array_bytes.value())?;
+                    let id = ids
+                        .iter()
+                        .skip_while(|id| **id != order.id.0)
+                        .nth(1)
+                        .map(|id| MyOrderId::from(*id));
+                    id
+                } else {
+                    None
+                }
+            };
+            if same_price_id.is_some() {
+                same_price_id
+            } else {
+                // first order of the next higher price level
+                let mut iter = price_index.range((key + 1)..)?;
+                if let Some((_, id_array_bytes)) = iter.next() {
+                    let ids = PriceIndexValue::decode(id_array_bytes.value())?;
+                    let id = ids.first().expect("index node must have at least one id");
+                    Some(MyOrderId::from(*id))
+                } else {
+                    None
+                }
+            }
+        };
+        if let Some(id) = id {
+            self.get_by_order_id(tx, &id)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn get_pegged_orders(
+        &self,
+        tx: &mut Self::Transaction,
+    ) -> std::result::Result<
+        Vec<LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>>,
+        Self::Err,
+    > {
+        let order_table = tx.open_table(ASK_LIMIT_ORDER_TABLE)?;
+        let mut orders = Vec::new();
+        for (id, order_bytes) in order_table.iter()? {
+            let order = LimitOrderValue::decode(order_bytes.value())?;
+            if order.peg_type.is_some() {
+                orders.push(order.into_order(
+                    MyOrderId::from(id.value()),
+                    self.asset_pair.clone(),
+                    OrderSide::Ask,
+                ));
+            }
+        }
+        Ok(orders)
+    }
 }
 
-pub struct MyMatchingEngine<'db> {
+pub struct MyStopOrderRepository<'db> {
+    #[allow(dead_code)]
     database: &'db redb::Database,
     asset_pair: MyAssetPair,
-    bid_limit_order_repository: MyBidLimitOrderRepository<'db>,
-    ask_limit_order_repository: MyAskLimitOrderRepository<'db>,
 }
 
-impl<'db> MyMatchingEngine<'db> {
-    fn start_tx(&self) -> Result<redb::WriteTransaction<'db>> {
-        self.database.begin_write().map_err(Into::into)
+impl<'db> MyStopOrderRepository<'db> {
+    fn new(database: &'db redb::Database, asset_pair: MyAssetPair) -> Self {
+        Self {
+            database,
+            asset_pair,
+        }
     }
-}
 
-impl<'db> MatchingEngine for MyMatchingEngine<'db> {
-    type Err = MyError;
-    type Asset = MyAsset;
-    type OrderId = MyOrderId;
-    type Price = MyPrice;
-    type Quantity = MyQuantity;
-    type Transaction = redb::WriteTransaction<'db>;
-    type BidLimitOrderRepository = MyBidLimitOrderRepository<'db>;
-    type AskLimitOrderRepository = MyAskLimitOrderRepository<'db>;
+    fn insert_or_update_order<'txn>(
+        &self,
+        order_table: &mut redb::Table<'db, 'txn, u128, &[u8]>,
+        order: &StopOrder<MyOrderId, MyAsset, MyPrice, MyQuantity>,
+    ) -> Result<()> {
+        let value = StopOrderValue::from_order(order);
+        let mut bytes = Vec::new();
+        value.encode(&mut bytes)?;
+        order_table.insert(&order.id.0, &bytes)?;
+        Ok(())
+    }
+
+    fn price_index(
+        order: &StopOrder<MyOrderId, MyAsset, MyPrice, MyQuantity>,
+    ) -> redb::TableDefinition<'static, u128, &'static [u8]> {
+        if order.triggers_on_rise() {
+            STOP_ORDER_RISE_PRICE_INDEX
+        } else {
+            STOP_ORDER_FALL_PRICE_INDEX
+        }
+    }
+
+    fn add_index_value<'txn>(
+        &self,
+        index: &mut redb::Table<'db, 'txn, u128, &[u8]>,
+        price: &MyPrice,
+        order_id: &MyOrderId,
+    ) -> Result<()> {
+        let key: u128 = (*price).into();
+        let mut value = if let Some(ids) = index.get(&key)? {
+            PriceIndexValue::decode(ids.value())?
+        } else {
+            PriceIndexValue::default()
+        };
+        value.push(&order_id);
+        let mut index_value_bytes = Vec::new();
+        value.encode(&mut index_value_bytes)?;
+        index.insert(&key, &index_value_bytes)?;
+        Ok(())
+    }
+
+    fn delete_index_value<'txn>(
+        &self,
+        index: &mut redb::Table<'db, 'txn, u128, &[u8]>,
+        price: &MyPrice,
+        order_id: &MyOrderId,
+    ) -> Result<()> {
+        let key: u128 = (*price).into();
+        let mut value = if let Some(ids) = index.get(&key)? {
+            PriceIndexValue::decode(ids.value())?
+        } else {
+            PriceIndexValue::default()
+        };
+        value.remove(&order_id);
+        if value.is_empty() {
+            index.remove(&key)?;
+        } else {
+            let mut index_value_bytes = Vec::new();
+            value.encode(&mut index_value_bytes)?;
+            index.insert(&key, &index_value_bytes)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'db> StopOrderRepositoryLike for MyStopOrderRepository<'db> {
+    type Err = MyError;
+    type Asset = MyAsset;
+    type OrderId = MyOrderId;
+    type Price = MyPrice;
+    type Quantity = MyQuantity;
+    type Transaction = redb::WriteTransaction<'db>;
+
+    fn create(
+        &self,
+        tx: &mut Self::Transaction,
+        order: &StopOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+    ) -> std::result::Result<(), Self::Err> {
+        // insert order
+        {
+            let mut stop_order_table = tx.open_table(STOP_ORDER_TABLE)?;
+            self.insert_or_update_order(&mut stop_order_table, order)?;
+        }
+        // update index
+        {
+            let mut price_index = tx.open_table(Self::price_index(order))?;
+            self.add_index_value(&mut price_index, &order.stop_price, &order.id)?;
+        }
+        Ok(())
+    }
+
+    fn update(
+        &self,
+        tx: &mut Self::Transaction,
+        order: &StopOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+    ) -> std::result::Result<(), Self::Err> {
+        if let Some(old_order) = self.get_by_order_id(tx, &order.id)? {
+            if old_order.stop_price != order.stop_price {
+                // delete & update index
+                let mut price_index = tx.open_table(Self::price_index(order))?;
+                self.delete_index_value(&mut price_index, &old_order.stop_price, &order.id)?;
+                self.add_index_value(&mut price_index, &order.stop_price, &order.id)?;
+            } else {
+                // no need to update index
+            }
+        } else {
+            // update index
+            let mut price_index = tx.open_table(Self::price_index(order))?;
+            self.add_index_value(&mut price_index, &order.stop_price, &order.id)?;
+        }
+        // update or insert order
+        {
+            let mut stop_order_table = tx.open_table(STOP_ORDER_TABLE)?;
+            self.insert_or_update_order(&mut stop_order_table, order)?;
+        }
+        Ok(())
+    }
+
+    fn delete_by_order_id(
+        &self,
+        tx: &mut Self::Transaction,
+        order_id: &Self::OrderId,
+    ) -> std::result::Result<(), Self::Err> {
+        if let Some(order) = self.get_by_order_id(tx, order_id)? {
+            // delete order
+            {
+                let mut stop_order_table = tx.open_table(STOP_ORDER_TABLE)?;
+                stop_order_table.remove(&order.id.0)?;
+            }
+            // delete from index
+            {
+                let mut price_index = tx.open_table(Self::price_index(&order))?;
+                self.delete_index_value(&mut price_index, &order.stop_price, &order.id)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn get_by_order_id(
+        &self,
+        tx: &mut Self::Transaction,
+        order_id: &Self::OrderId,
+    ) -> std::result::Result<
+        Option<StopOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>>,
+        Self::Err,
+    > {
+        let stop_order_table = tx.open_table(STOP_ORDER_TABLE)?;
+        let order = if let Some(order_bytes) = stop_order_table.get(&order_id.0)? {
+            let order = StopOrderValue::decode(order_bytes.value())?;
+            Some(order.into_order(order_id.clone(), self.asset_pair.clone()))
+        } else {
+            None
+        };
+        Ok(order)
+    }
+
+    fn get_trailing_stop_orders(
+        &self,
+        tx: &mut Self::Transaction,
+    ) -> std::result::Result<
+        Vec<StopOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>>,
+        Self::Err,
+    > {
+        let stop_order_table = tx.open_table(STOP_ORDER_TABLE)?;
+        let mut orders = Vec::new();
+        for (id, order_bytes) in stop_order_table.iter()? {
+            let order = StopOrderValue::decode(order_bytes.value())?;
+            if order.trailing_offset.is_some() || order.trailing_offset_bps.is_some() {
+                orders.push(order.into_order(MyOrderId::from(id.value()), self.asset_pair.clone()));
+            }
+        }
+        Ok(orders)
+    }
+
+    fn next_triggered(
+        &self,
+        tx: &mut Self::Transaction,
+        last_price: &Self::Price,
+    ) -> std::result::Result<
+        Option<StopOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>>,
+        Self::Err,
+    > {
+        let key: u128 = (*last_price).into();
+        let id = {
+            // orders with a stop price at or below the last price
+            let rise_price_index = tx.open_table(STOP_ORDER_RISE_PRICE_INDEX)?;
+            let mut iter = rise_price_index.range(..=key)?;
+            if let Some((_, id_array_bytes)) = iter.next() {
+                let ids = PriceIndexValue::decode(id_array_bytes.value())?;
+                let id = ids.first().expect("index node must have at least one id");
+                Some(MyOrderId::from(*id))
+            } else {
+                None
+            }
+        };
+        let id = if id.is_some() {
+            id
+        } else {
+            // orders with a stop price at or above the last price
+            let fall_price_index = tx.open_table(STOP_ORDER_FALL_PRICE_INDEX)?;
+            let mut iter = fall_price_index.range(key..)?;
+            if let Some((_, id_array_bytes)) = iter.next_back() {
+                let ids = PriceIndexValue::decode(id_array_bytes.value())?;
+                let id = ids.first().expect("index node must have at least one id");
+                Some(MyOrderId::from(*id))
+            } else {
+                None
+            }
+        };
+        if let Some(id) = id {
+            self.get_by_order_id(tx, &id)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+pub struct MyOrderLinkRepository<'db> {
+    #[allow(dead_code)]
+    database: &'db redb::Database,
+}
+
+impl<'db> MyOrderLinkRepository<'db> {
+    fn new(database: &'db redb::Database) -> Self {
+        Self { database }
+    }
+}
+
+impl<'db> OrderLinkRepositoryLike for MyOrderLinkRepository<'db> {
+    type Err = MyError;
+    type OrderId = MyOrderId;
+    type Transaction = redb::WriteTransaction<'db>;
+
+    fn create(
+        &self,
+        tx: &mut Self::Transaction,
+        link: &OrderLink<Self::OrderId>,
+    ) -> std::result::Result<(), Self::Err> {
+        let value = OrderLinkValue::from_link(link);
+        let mut bytes = Vec::new();
+        value.encode(&mut bytes)?;
+        let mut order_link_table = tx.open_table(ORDER_LINK_TABLE)?;
+        order_link_table.insert(&link.order_id.0, &bytes)?;
+        Ok(())
+    }
+
+    fn delete_by_order_id(
+        &self,
+        tx: &mut Self::Transaction,
+        order_id: &Self::OrderId,
+    ) -> std::result::Result<(), Self::Err> {
+        let mut order_link_table = tx.open_table(ORDER_LINK_TABLE)?;
+        order_link_table.remove(&order_id.0)?;
+        Ok(())
+    }
+
+    fn get_by_order_id(
+        &self,
+        tx: &mut Self::Transaction,
+        order_id: &Self::OrderId,
+    ) -> std::result::Result<Option<OrderLink<Self::OrderId>>, Self::Err> {
+        let order_link_table = tx.open_table(ORDER_LINK_TABLE)?;
+        let link = if let Some(link_bytes) = order_link_table.get(&order_id.0)? {
+            let link = OrderLinkValue::decode(link_bytes.value())?;
+            Some(link.into_link(order_id.clone()))
+        } else {
+            None
+        };
+        Ok(link)
+    }
+}
+
+pub struct MyChildOrderRepository<'db> {
+    #[allow(dead_code)]
+    database: &'db redb::Database,
+    asset_pair: MyAssetPair,
+}
+
+impl<'db> MyChildOrderRepository<'db> {
+    fn new(database: &'db redb::Database, asset_pair: MyAssetPair) -> Self {
+        Self {
+            database,
+            asset_pair,
+        }
+    }
+}
+
+impl<'db> ChildOrderRepositoryLike for MyChildOrderRepository<'db> {
+    type Err = MyError;
+    type Asset = MyAsset;
+    type OrderId = MyOrderId;
+    type Price = MyPrice;
+    type Quantity = MyQuantity;
+    type Transaction = redb::WriteTransaction<'db>;
+
+    fn create(
+        &self,
+        tx: &mut Self::Transaction,
+        child_orders: &ChildOrders<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+    ) -> std::result::Result<(), Self::Err> {
+        let value = ChildOrdersValue::from_child_orders(child_orders);
+        let mut bytes = Vec::new();
+        value.encode(&mut bytes)?;
+        let mut child_order_table = tx.open_table(CHILD_ORDER_TABLE)?;
+        child_order_table.insert(&child_orders.parent_id.0, &bytes)?;
+        Ok(())
+    }
+
+    fn update(
+        &self,
+        tx: &mut Self::Transaction,
+        child_orders: &ChildOrders<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+    ) -> std::result::Result<(), Self::Err> {
+        self.create(tx, child_orders)
+    }
+
+    fn delete_by_parent_id(
+        &self,
+        tx: &mut Self::Transaction,
+        parent_id: &Self::OrderId,
+    ) -> std::result::Result<(), Self::Err> {
+        let mut child_order_table = tx.open_table(CHILD_ORDER_TABLE)?;
+        child_order_table.remove(&parent_id.0)?;
+        Ok(())
+    }
+
+    fn get_by_parent_id(
+        &self,
+        tx: &mut Self::Transaction,
+        parent_id: &Self::OrderId,
+    ) -> std::result::Result<
+        Option<ChildOrders<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>>,
+        Self::Err,
+    > {
+        let child_order_table = tx.open_table(CHILD_ORDER_TABLE)?;
+        let child_orders = if let Some(bytes) = child_order_table.get(&parent_id.0)? {
+            let value = ChildOrdersValue::decode(bytes.value())?;
+            Some(value.into_child_orders(parent_id.clone(), self.asset_pair.clone()))
+        } else {
+            None
+        };
+        Ok(child_orders)
+    }
+}
+
+/// a single trading session, in pre-open until it opens.
+pub struct MyTradingSchedule {
+    open_time_ms: u64,
+    close_time_ms: u64,
+}
+
+impl MyTradingSchedule {
+    fn new(open_time_ms: u64, close_time_ms: u64) -> Self {
+        Self {
+            open_time_ms,
+            close_time_ms,
+        }
+    }
+}
+
+impl TradingScheduleLike for MyTradingSchedule {
+    fn session_state(&self, timestamp_ms: u64) -> SessionState {
+        if timestamp_ms < self.open_time_ms {
+            SessionState::PreOpen
+        } else if timestamp_ms < self.close_time_ms {
+            SessionState::Continuous
+        } else {
+            SessionState::Closed
+        }
+    }
+
+    fn session_close_time_ms(&self, _timestamp_ms: u64) -> Option<u64> {
+        Some(self.close_time_ms)
+    }
+}
+
+pub struct MyMatchingEngine<'db> {
+    database: &'db redb::Database,
+    asset_pair: MyAssetPair,
+    bid_limit_order_repository: MyBidLimitOrderRepository<'db>,
+    ask_limit_order_repository: MyAskLimitOrderRepository<'db>,
+    stop_order_repository: MyStopOrderRepository<'db>,
+    order_link_repository: MyOrderLinkRepository<'db>,
+    child_order_repository: MyChildOrderRepository<'db>,
+    trading_schedule: MyTradingSchedule,
+}
+
+impl<'db> MyMatchingEngine<'db> {
+    fn start_tx(&self) -> Result<redb::WriteTransaction<'db>> {
+        self.database.begin_write().map_err(Into::into)
+    }
+}
+
+impl<'db> MatchingEngine for MyMatchingEngine<'db> {
+    type Err = MyError;
+    type Asset = MyAsset;
+    type OrderId = MyOrderId;
+    type Price = MyPrice;
+    type Quantity = MyQuantity;
+    type Transaction = redb::WriteTransaction<'db>;
+    type BidLimitOrderRepository = MyBidLimitOrderRepository<'db>;
+    type AskLimitOrderRepository = MyAskLimitOrderRepository<'db>;
+    type StopOrderRepository = MyStopOrderRepository<'db>;
+    type OrderLinkRepository = MyOrderLinkRepository<'db>;
+    type ChildOrderRepository = MyChildOrderRepository<'db>;
+    type TradingSchedule = MyTradingSchedule;
 
     fn asset_pair(&self) -> &AssetPair<Self::Asset> {
         &self.asset_pair
@@ -695,6 +1644,26 @@ impl<'db> MatchingEngine for MyMatchingEngine<'db> {
     fn ask_limit_order_repository(&self) -> &Self::AskLimitOrderRepository {
         &self.ask_limit_order_repository
     }
+
+    fn stop_order_repository(&self) -> &Self::StopOrderRepository {
+        &self.stop_order_repository
+    }
+
+    fn order_link_repository(&self) -> &Self::OrderLinkRepository {
+        &self.order_link_repository
+    }
+
+    fn child_order_repository(&self) -> &Self::ChildOrderRepository {
+        &self.child_order_repository
+    }
+
+    fn trading_schedule(&self) -> &Self::TradingSchedule {
+        &self.trading_schedule
+    }
+
+    fn tick_size(&self) -> Self::Price {
+        MyPrice::new(1, 2)
+    }
 }
 
 fn main() -> Result<()> {
@@ -705,24 +1674,31 @@ fn main() -> Result<()> {
         asset_pair: asset_pair.clone(),
         bid_limit_order_repository: MyBidLimitOrderRepository::new(&database, asset_pair.clone()),
         ask_limit_order_repository: MyAskLimitOrderRepository::new(&database, asset_pair.clone()),
+        stop_order_repository: MyStopOrderRepository::new(&database, asset_pair.clone()),
+        order_link_repository: MyOrderLinkRepository::new(&database),
+        child_order_repository: MyChildOrderRepository::new(&database, asset_pair.clone()),
+        trading_schedule: MyTradingSchedule::new(
+            current_timestamp_ms(),
+            current_timestamp_ms() + 8 * 60 * 60 * 1_000,
+        ),
     };
     let mut order_requests = vec![
-        OrderRequest::Limit(LimitOrder {
-            id: MyOrderId::new(),
-            asset_pair: asset_pair.clone(),
-            side: OrderSide::Bid,
-            price: MyPrice::new(98, 2),
-            quantity: MyQuantity::new(50, 1),
-            timestamp_ms: current_timestamp_ms(),
-        }),
-        OrderRequest::Limit(LimitOrder {
-            id: MyOrderId::new(),
-            asset_pair: asset_pair.clone(),
-            side: OrderSide::Ask,
-            price: MyPrice::new(102, 2),
-            quantity: MyQuantity::new(10, 1),
-            timestamp_ms: current_timestamp_ms(),
-        }),
+        OrderRequest::Limit(LimitOrder::new(
+            MyOrderId::new(),
+            asset_pair.clone(),
+            OrderSide::Bid,
+            MyPrice::new(98, 2),
+            MyQuantity::new(50, 1),
+            current_timestamp_ms(),
+        )),
+        OrderRequest::Limit(LimitOrder::new(
+            MyOrderId::new(),
+            asset_pair.clone(),
+            OrderSide::Ask,
+            MyPrice::new(102, 2),
+            MyQuantity::new(10, 1),
+            current_timestamp_ms(),
+        )),
     ];
     if let OrderRequest::Limit(first_limit_order) = order_requests.get(0).unwrap() {
         order_requests.push(OrderRequest::Amend(AmendOrder {
@@ -732,41 +1708,127 @@ fn main() -> Result<()> {
             target_order_type: OrderType::Limit,
             side: first_limit_order.side,
             price: MyPrice::new(99, 2),
+            stop_price: None,
             quantity: MyQuantity::new(40, 1),
             timestamp_ms: current_timestamp_ms(),
         }));
     }
-    order_requests.push(OrderRequest::Limit(LimitOrder {
-        id: MyOrderId::new(),
-        asset_pair: asset_pair.clone(),
-        side: OrderSide::Bid,
-        price: MyPrice::new(101, 2),
-        quantity: MyQuantity::new(4, 1),
-        timestamp_ms: current_timestamp_ms(),
-    }));
-    order_requests.push(OrderRequest::Limit(LimitOrder {
-        id: MyOrderId::new(),
-        asset_pair: asset_pair.clone(),
-        side: OrderSide::Ask,
-        price: MyPrice::new(103, 2),
-        quantity: MyQuantity::new(5, 1),
-        timestamp_ms: current_timestamp_ms(),
-    }));
+    order_requests.push(OrderRequest::Limit(LimitOrder::new(
+        MyOrderId::new(),
+        asset_pair.clone(),
+        OrderSide::Bid,
+        MyPrice::new(101, 2),
+        MyQuantity::new(4, 1),
+        current_timestamp_ms(),
+    )));
+    order_requests.push(OrderRequest::Limit(LimitOrder::new(
+        MyOrderId::new(),
+        asset_pair.clone(),
+        OrderSide::Ask,
+        MyPrice::new(103, 2),
+        MyQuantity::new(5, 1),
+        current_timestamp_ms(),
+    )));
+    order_requests.push(OrderRequest::new_stop_loss_limit(
+        MyOrderId::new(),
+        asset_pair.clone(),
+        OrderSide::Ask,
+        MyPrice::new(100, 2),
+        MyPrice::new(99, 2),
+        MyQuantity::new(5, 1),
+        current_timestamp_ms(),
+    ));
+    order_requests.push(OrderRequest::new_hidden(
+        MyOrderId::new(),
+        asset_pair.clone(),
+        OrderSide::Ask,
+        MyPrice::new(107, 2),
+        MyQuantity::new(10, 1),
+        current_timestamp_ms(),
+    ));
+    // orders of the same owner do not trade with each other
+    order_requests.push(
+        OrderRequest::new_limit(
+            MyOrderId::new(),
+            asset_pair.clone(),
+            OrderSide::Ask,
+            MyPrice::new(108, 2),
+            MyQuantity::new(5, 1),
+            TimeInForce::GTC,
+            current_timestamp_ms(),
+        )
+        .with_owner(1),
+    );
+    order_requests.push(
+        OrderRequest::new_limit(
+            MyOrderId::new(),
+            asset_pair.clone(),
+            OrderSide::Bid,
+            MyPrice::new(108, 2),
+            MyQuantity::new(5, 1),
+            TimeInForce::IOC,
+            current_timestamp_ms(),
+        )
+        .with_owner(1),
+    );
+    order_requests.push(OrderRequest::new_take_profit(
+        MyOrderId::new(),
+        asset_pair.clone(),
+        OrderSide::Ask,
+        MyPrice::new(104, 2),
+        MyQuantity::new(1, 1),
+        current_timestamp_ms(),
+    ));
+    order_requests.push(OrderRequest::new_trailing_stop(
+        MyOrderId::new(),
+        asset_pair.clone(),
+        OrderSide::Ask,
+        MyPrice::new(95, 2),
+        TrailingOffset::BasisPoints(200),
+        MyQuantity::new(1, 1),
+        current_timestamp_ms(),
+    ));
     order_requests.push(OrderRequest::Market(MarketOrder {
         id: MyOrderId::new(),
         asset_pair: asset_pair.clone(),
         side: OrderSide::Bid,
-        quantity: MyQuantity::new(10, 1),
-        timestamp_ms: current_timestamp_ms(),
-    }));
-    order_requests.push(OrderRequest::Limit(LimitOrder {
-        id: MyOrderId::new(),
-        asset_pair: asset_pair.clone(),
-        side: OrderSide::Ask,
-        price: MyPrice::new(105, 2),
-        quantity: MyQuantity::new(5, 1),
+        owner: None,
+        quantity: MarketOrderQuantity::Base(MyQuantity::new(10, 1)),
+        price_protection: None,
+        execution_constraint: None,
+        market_to_limit: false,
         timestamp_ms: current_timestamp_ms(),
     }));
+    order_requests.push(OrderRequest::new_market_to_limit(
+        MyOrderId::new(),
+        asset_pair.clone(),
+        OrderSide::Bid,
+        MyQuantity::new(10, 1),
+        current_timestamp_ms(),
+    ));
+    order_requests.push(OrderRequest::new_market_quote(
+        MyOrderId::new(),
+        asset_pair.clone(),
+        OrderSide::Bid,
+        MyQuantity::new(50, 2),
+        current_timestamp_ms(),
+    ));
+    order_requests.push(OrderRequest::new_protected_market(
+        MyOrderId::new(),
+        asset_pair.clone(),
+        OrderSide::Ask,
+        MarketOrderQuantity::Base(MyQuantity::new(20, 1)),
+        PriceProtection::BasisPoints(100),
+        current_timestamp_ms(),
+    ));
+    order_requests.push(OrderRequest::Limit(LimitOrder::new(
+        MyOrderId::new(),
+        asset_pair.clone(),
+        OrderSide::Ask,
+        MyPrice::new(105, 2),
+        MyQuantity::new(5, 1),
+        current_timestamp_ms(),
+    )));
     if let OrderRequest::Limit(fourth_limit_order) = order_requests.get(4).unwrap() {
         order_requests.push(OrderRequest::Cancel(CancelOrder {
             id: MyOrderId::new(),
@@ -776,14 +1838,126 @@ fn main() -> Result<()> {
             side: fourth_limit_order.side,
         }));
     }
+    order_requests.push(OrderRequest::new_good_till_date(
+        MyOrderId::new(),
+        asset_pair.clone(),
+        OrderSide::Bid,
+        MyPrice::new(97, 2),
+        MyQuantity::new(10, 1),
+        current_timestamp_ms() + 1_000,
+        current_timestamp_ms(),
+    ));
+    order_requests.push(OrderRequest::new_limit(
+        MyOrderId::new(),
+        asset_pair.clone(),
+        OrderSide::Bid,
+        MyPrice::new(96, 2),
+        MyQuantity::new(10, 1),
+        TimeInForce::DAY,
+        current_timestamp_ms(),
+    ));
+    order_requests.push(OrderRequest::new_iceberg(
+        MyOrderId::new(),
+        asset_pair.clone(),
+        OrderSide::Ask,
+        MyPrice::new(107, 2),
+        MyQuantity::new(30, 1),
+        MyQuantity::new(5, 1),
+        current_timestamp_ms(),
+    ));
+    order_requests.push(OrderRequest::new_limit_maker(
+        MyOrderId::new(),
+        asset_pair.clone(),
+        OrderSide::Bid,
+        MyPrice::new(105, 2),
+        MyQuantity::new(2, 1),
+        PostOnly::Reprice,
+        current_timestamp_ms(),
+    ));
     order_requests.push(OrderRequest::Limit(LimitOrder {
-        id: MyOrderId::new(),
-        asset_pair: asset_pair.clone(),
-        side: OrderSide::Bid,
-        price: MyPrice::new(106, 2),
-        quantity: MyQuantity::new(6, 1),
-        timestamp_ms: current_timestamp_ms(),
+        time_in_force: TimeInForce::IOC,
+        ..LimitOrder::new(
+            MyOrderId::new(),
+            asset_pair.clone(),
+            OrderSide::Bid,
+            MyPrice::new(106, 2),
+            MyQuantity::new(6, 1),
+            current_timestamp_ms(),
+        )
     }));
+    order_requests.push(OrderRequest::new_pegged(
+        MyOrderId::new(),
+        asset_pair.clone(),
+        OrderSide::Bid,
+        PegType::Primary,
+        Some(MyPrice::new(1, 2)),
+        Some(MyPrice::new(104, 2)),
+        MyQuantity::new(3, 1),
+        current_timestamp_ms(),
+    ));
+    order_requests.push(OrderRequest::new_constrained_limit(
+        MyOrderId::new(),
+        asset_pair.clone(),
+        OrderSide::Bid,
+        MyPrice::new(108, 2),
+        MyQuantity::new(20, 1),
+        TimeInForce::IOC,
+        ExecutionConstraint::MinQuantity(MyQuantity::new(10, 1)),
+        current_timestamp_ms(),
+    ));
+    order_requests.push(OrderRequest::new_oco(
+        LimitOrder::new(
+            MyOrderId::new(),
+            asset_pair.clone(),
+            OrderSide::Ask,
+            MyPrice::new(110, 2),
+            MyQuantity::new(5, 1),
+            current_timestamp_ms(),
+        ),
+        StopOrder {
+            id: MyOrderId::new(),
+            asset_pair: asset_pair.clone(),
+            order_type: OrderType::StopLoss,
+            side: OrderSide::Ask,
+            owner: None,
+            stop_price: MyPrice::new(90, 2),
+            price: None,
+            quantity: MyQuantity::new(5, 1),
+            trailing_stop: None,
+            timestamp_ms: current_timestamp_ms(),
+        },
+    ));
+    order_requests.push(OrderRequest::new_bracket(
+        LimitOrder::new(
+            MyOrderId::new(),
+            asset_pair.clone(),
+            OrderSide::Bid,
+            MyPrice::new(100, 2),
+            MyQuantity::new(5, 1),
+            current_timestamp_ms(),
+        ),
+        // child orders are sized to the filled quantity of the entry order
+        LimitOrder::new(
+            MyOrderId::new(),
+            asset_pair.clone(),
+            OrderSide::Ask,
+            MyPrice::new(112, 2),
+            MyQuantity::new(0, 1),
+            current_timestamp_ms(),
+        ),
+        StopOrder {
+            id: MyOrderId::new(),
+            asset_pair: asset_pair.clone(),
+            order_type: OrderType::StopLoss,
+            side: OrderSide::Ask,
+            owner: None,
+            stop_price: MyPrice::new(92, 2),
+            price: None,
+            quantity: MyQuantity::new(0, 1),
+            trailing_stop: None,
+            timestamp_ms: current_timestamp_ms(),
+        },
+    ));
 
     // processing
     for order_request in order_requests {
@@ -795,6 +1969,26 @@ fn main() -> Result<()> {
         }
         tx.commit()?;
     }
+
+    // uncross of the orders collected in a call auction
+    {
+        let mut tx = my_engine.start_tx()?;
+        {
+            let res = my_engine.uncross(&mut tx)?;
+            println!("Uncross => {:?}", res);
+        }
+        tx.commit()?;
+    }
+
+    // expiry sweep
+    {
+        let mut tx = my_engine.start_tx()?;
+        {
+            let res = my_engine.expire_orders(&mut tx, current_timestamp_ms() + 2_000)?;
+            println!("Expiry => {:?}", res);
+        }
+        tx.commit()?;
+    }
     Ok(())
 }
 
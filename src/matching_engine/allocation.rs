@@ -0,0 +1,353 @@
+use crate::{Allocation, MatchingPolicy};
+
+/// an order of the price level among which the incoming quantity is allocated.
+pub(crate) struct LevelOrder<Q> {
+    pub quantity: Q,
+    pub top_order: bool,
+    pub lead_market_maker: bool,
+}
+
+/// splits `quantity` across the orders of a price level, given in time priority, by the
+/// matching policy. the allocation is deterministic for the same level.
+/// returns the allocation of each order, `None` if the order receives nothing.
+///
+/// panics if the lot size of the policy is not positive.
+pub(crate) fn allocations<Q: Allocation + Default>(
+    policy: MatchingPolicy<Q>,
+    quantity: Q,
+    level_orders: &[LevelOrder<Q>],
+) -> Vec<Option<Q>> {
+    if let MatchingPolicy::ProRata { lot_size, .. }
+    | MatchingPolicy::TopOrderProRata { lot_size, .. } = policy
+    {
+        assert!(lot_size > Q::default(), "lot size must be positive");
+    }
+    let mut allocations = vec![None; level_orders.len()];
+    match policy {
+        MatchingPolicy::Fifo => {
+            allocate_in_time_priority(&mut allocations, Some(quantity), level_orders, |_| true);
+        }
+        MatchingPolicy::ProRata {
+            minimum_allocation,
+            lot_size,
+        } => {
+            allocate_pro_rata(
+                &mut allocations,
+                quantity,
+                level_orders,
+                |_| true,
+                minimum_allocation,
+                lot_size,
+            );
+        }
+        MatchingPolicy::TopOrderProRata {
+            lead_market_maker_basis_points,
+            minimum_allocation,
+            lot_size,
+        } => {
+            let mut remaining = Some(quantity);
+            if matches!(level_orders.first(), Some(order) if order.top_order) {
+                remaining =
+                    allocate_in_time_priority(&mut allocations, remaining, level_orders, |i| {
+                        i == 0
+                    });
+            }
+            if let Some(rest) = remaining {
+                if let Some(lead_market_maker_quantity) =
+                    rest.fraction(lead_market_maker_basis_points, lot_size)
+                {
+                    let unallocated = allocate_pro_rata(
+                        &mut allocations,
+                        lead_market_maker_quantity,
+                        level_orders,
+                        |i| level_orders[i].lead_market_maker,
+                        minimum_allocation,
+                        lot_size,
+                    );
+                    let allocated = match unallocated {
+                        Some(unallocated) => lead_market_maker_quantity - unallocated,
+                        None => lead_market_maker_quantity,
+                    };
+                    remaining = if allocated < rest {
+                        Some(rest - allocated)
+                    } else {
+                        None
+                    };
+                }
+            }
+            if let Some(rest) = remaining {
+                allocate_pro_rata(
+                    &mut allocations,
+                    rest,
+                    level_orders,
+                    |_| true,
+                    minimum_allocation,
+                    lot_size,
+                );
+            }
+        }
+    }
+    allocations
+}
+
+/// quantity of the order not allocated yet. `None` if nothing is left.
+fn room<Q: Allocation>(level_order: &LevelOrder<Q>, allocation: Option<Q>) -> Option<Q> {
+    match allocation {
+        Some(allocated) if allocated >= level_order.quantity => None,
+        Some(allocated) => Some(level_order.quantity - allocated),
+        None => Some(level_order.quantity),
+    }
+}
+
+fn add<Q: Allocation>(allocation: &mut Option<Q>, quantity: Q) {
+    *allocation = Some(allocation.map_or(quantity, |allocated| allocated + quantity));
+}
+
+/// allocates `remaining` to the eligible orders one at a time.
+/// returns the quantity left unallocated.
+fn allocate_in_time_priority<Q: Allocation>(
+    allocations: &mut [Option<Q>],
+    remaining: Option<Q>,
+    level_orders: &[LevelOrder<Q>],
+    is_eligible: impl Fn(usize) -> bool,
+) -> Option<Q> {
+    let mut remaining = remaining;
+    for (i, level_order) in level_orders.iter().enumerate() {
+        let rest = match remaining {
+            Some(rest) => rest,
+            None => break,
+        };
+        let room = match room(level_order, allocations[i]) {
+            Some(room) if is_eligible(i) => room,
+            _ => continue,
+        };
+        let extra = rest.min(room);
+        add(&mut allocations[i], extra);
+        remaining = if extra < rest {
+            Some(rest - extra)
+        } else {
+            None
+        };
+    }
+    remaining
+}
+
+/// allocates `quantity` to the eligible orders in proportion to their unallocated quantities.
+/// each share is rounded down to a multiple of `lot_size` and dropped if less than
+/// `minimum_allocation`, and the quantity left by the rounding is allocated in time priority.
+/// returns the quantity left unallocated.
+fn allocate_pro_rata<Q: Allocation>(
+    allocations: &mut [Option<Q>],
+    quantity: Q,
+    level_orders: &[LevelOrder<Q>],
+    is_eligible: impl Fn(usize) -> bool,
+    minimum_allocation: Option<Q>,
+    lot_size: Q,
+) -> Option<Q> {
+    let rooms: Vec<(usize, Q)> = level_orders
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| is_eligible(*i))
+        .filter_map(|(i, level_order)| room(level_order, allocations[i]).map(|room| (i, room)))
+        .collect();
+    let total_room = match rooms.iter().map(|(_, room)| *room).reduce(|a, b| a + b) {
+        Some(total_room) => total_room,
+        None => return Some(quantity),
+    };
+    if quantity >= total_room {
+        // every eligible order is filled
+        for (i, room) in rooms {
+            add(&mut allocations[i], room);
+        }
+        return if quantity > total_room {
+            Some(quantity - total_room)
+        } else {
+            None
+        };
+    }
+    let mut remaining = Some(quantity);
+    for (i, room) in rooms.iter() {
+        let share = quantity
+            .allocation(*room, total_room, lot_size)
+            .filter(|share| match minimum_allocation {
+                Some(minimum) => *share >= minimum,
+                None => true,
+            });
+        if let (Some(share), Some(rest)) = (share, remaining) {
+            let share = share.min(rest);
+            add(&mut allocations[*i], share);
+            remaining = if share < rest {
+                Some(rest - share)
+            } else {
+                None
+            };
+        }
+    }
+    allocate_in_time_priority(allocations, remaining, level_orders, is_eligible)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level_orders(quantities: &[u64]) -> Vec<LevelOrder<u64>> {
+        quantities
+            .iter()
+            .map(|quantity| LevelOrder {
+                quantity: *quantity,
+                top_order: false,
+                lead_market_maker: false,
+            })
+            .collect()
+    }
+
+    fn pro_rata(minimum_allocation: Option<u64>, lot_size: u64) -> MatchingPolicy<u64> {
+        MatchingPolicy::ProRata {
+            minimum_allocation,
+            lot_size,
+        }
+    }
+
+    #[test]
+    fn fifo_fills_orders_in_time_priority() {
+        assert_eq!(
+            allocations(MatchingPolicy::Fifo, 7, &level_orders(&[5, 5, 5])),
+            vec![Some(5), Some(2), None]
+        );
+    }
+
+    #[test]
+    fn pro_rata_allocates_in_proportion() {
+        assert_eq!(
+            allocations(pro_rata(None, 1), 10, &level_orders(&[30, 50, 20])),
+            vec![Some(3), Some(5), Some(2)]
+        );
+    }
+
+    #[test]
+    fn pro_rata_rounds_shares_down_to_the_lot_size() {
+        // shares of 3, 5 and 2 are rounded down to 2, 4 and 2. the rest goes to the oldest order
+        assert_eq!(
+            allocations(pro_rata(None, 2), 10, &level_orders(&[30, 50, 20])),
+            vec![Some(4), Some(4), Some(2)]
+        );
+    }
+
+    #[test]
+    fn pro_rata_allocates_the_leftover_in_time_priority() {
+        // every share is less than a lot
+        assert_eq!(
+            allocations(pro_rata(None, 1), 4, &level_orders(&[3, 3, 3, 3, 3])),
+            vec![Some(3), Some(1), None, None, None]
+        );
+    }
+
+    #[test]
+    fn pro_rata_fills_every_order_when_the_quantity_covers_the_level() {
+        assert_eq!(
+            allocations(pro_rata(None, 1), 10, &level_orders(&[2, 3])),
+            vec![Some(2), Some(3)]
+        );
+    }
+
+    #[test]
+    fn pro_rata_drops_shares_below_the_minimum_allocation() {
+        assert_eq!(
+            allocations(pro_rata(Some(4), 1), 10, &level_orders(&[60, 40])),
+            vec![Some(6), Some(4)]
+        );
+        assert_eq!(
+            allocations(pro_rata(Some(5), 1), 10, &level_orders(&[60, 40])),
+            vec![Some(10), None]
+        );
+    }
+
+    #[test]
+    fn top_order_pro_rata_without_top_order_allocates_pro_rata() {
+        let policy = MatchingPolicy::TopOrderProRata {
+            lead_market_maker_basis_points: 0,
+            minimum_allocation: None,
+            lot_size: 1,
+        };
+        assert_eq!(
+            allocations(policy, 10, &level_orders(&[30, 50, 20])),
+            vec![Some(3), Some(5), Some(2)]
+        );
+    }
+
+    fn top_order_pro_rata(lead_market_maker_basis_points: u64) -> MatchingPolicy<u64> {
+        MatchingPolicy::TopOrderProRata {
+            lead_market_maker_basis_points,
+            minimum_allocation: None,
+            lot_size: 1,
+        }
+    }
+
+    #[test]
+    fn top_order_is_filled_first() {
+        let mut orders = level_orders(&[10, 20, 30]);
+        orders[0].top_order = true;
+        assert_eq!(
+            allocations(top_order_pro_rata(0), 5, &orders),
+            vec![Some(5), None, None]
+        );
+        // the rest is allocated pro-rata across the other orders
+        assert_eq!(
+            allocations(top_order_pro_rata(0), 40, &orders),
+            vec![Some(10), Some(12), Some(18)]
+        );
+    }
+
+    #[test]
+    fn top_order_only_counts_at_the_front_of_the_level() {
+        let mut orders = level_orders(&[30, 10]);
+        orders[1].top_order = true;
+        assert_eq!(
+            allocations(top_order_pro_rata(0), 8, &orders),
+            vec![Some(6), Some(2)]
+        );
+    }
+
+    #[test]
+    fn lead_market_makers_receive_their_share_before_the_pro_rata() {
+        let mut orders = level_orders(&[40, 40, 20]);
+        orders[1].lead_market_maker = true;
+        orders[2].lead_market_maker = true;
+        // 20% of 50 goes to the lead market makers: 6 and 3 pro-rata, then 1 in time priority.
+        // the remaining 40 is allocated pro-rata across the rest of the orders.
+        assert_eq!(
+            allocations(top_order_pro_rata(2_000), 50, &orders),
+            vec![Some(19), Some(21), Some(10)]
+        );
+    }
+
+    #[test]
+    fn lead_market_maker_share_is_capped_by_their_quantity() {
+        let mut orders = level_orders(&[100, 2]);
+        orders[1].lead_market_maker = true;
+        // the lead market maker share of 25 is capped at 2, and the rest goes to the pro-rata
+        assert_eq!(
+            allocations(top_order_pro_rata(5_000), 50, &orders),
+            vec![Some(48), Some(2)]
+        );
+    }
+
+    #[test]
+    fn top_order_then_lead_market_makers_then_pro_rata() {
+        let mut orders = level_orders(&[10, 45, 45]);
+        orders[0].top_order = true;
+        orders[2].lead_market_maker = true;
+        // 10 to the top order, 10% of the remaining 40 to the lead market maker, and the
+        // remaining 36 pro-rata across 45 and 41
+        assert_eq!(
+            allocations(top_order_pro_rata(1_000), 50, &orders),
+            vec![Some(10), Some(19), Some(21)]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "lot size must be positive")]
+    fn pro_rata_rejects_a_zero_lot_size() {
+        allocations(pro_rata(None, 0), 10, &level_orders(&[30, 50, 20]));
+    }
+}
@@ -0,0 +1,169 @@
+use crate::{AuctionClearing, OrderSide, Price, Quantity};
+use std::cmp::Ordering;
+
+/// finds the single price at which the collected orders of a call auction are executed.
+/// `bid_orders` and `ask_orders` are the prices and quantities of the orders in the book.
+///
+/// the clearing price maximises the executed quantity. ties are broken by the minimum imbalance
+/// between the bid and ask quantities crossing the price, then by the distance to
+/// `reference_price`, and finally by the lowest price.
+/// returns `None` if the book is not crossed.
+pub(crate) fn clearing_price<P: Price, Q: Quantity>(
+    bid_orders: &[(P, Q)],
+    ask_orders: &[(P, Q)],
+    reference_price: Option<P>,
+) -> Option<AuctionClearing<P, Q>> {
+    let mut prices: Vec<P> = bid_orders
+        .iter()
+        .chain(ask_orders.iter())
+        .map(|(price, _)| *price)
+        .collect();
+    prices.sort();
+    prices.dedup();
+    // the quantities crossing each price are accumulated once over the sorted prices
+    let mut ask_orders = ask_orders.to_vec();
+    ask_orders.sort_by_key(|(price, _)| *price);
+    let ask_quantities =
+        cumulative_quantities(prices.iter(), ask_orders.iter(), |p, price| p <= price);
+    let mut bid_orders = bid_orders.to_vec();
+    bid_orders.sort_by_key(|(price, _)| *price);
+    let mut bid_quantities =
+        cumulative_quantities(prices.iter().rev(), bid_orders.iter().rev(), |p, price| {
+            p >= price
+        });
+    bid_quantities.reverse();
+
+    let mut best: Option<AuctionClearing<P, Q>> = None;
+    for ((price, bid_quantity), ask_quantity) in
+        prices.into_iter().zip(bid_quantities).zip(ask_quantities)
+    {
+        let (bid_quantity, ask_quantity) = match (bid_quantity, ask_quantity) {
+            (Some(bid_quantity), Some(ask_quantity)) => (bid_quantity, ask_quantity),
+            _ => continue,
+        };
+        let candidate = AuctionClearing {
+            price,
+            matched_quantity: bid_quantity.min(ask_quantity),
+            imbalance: match bid_quantity.cmp(&ask_quantity) {
+                Ordering::Greater => Some((OrderSide::Bid, bid_quantity - ask_quantity)),
+                Ordering::Less => Some((OrderSide::Ask, ask_quantity - bid_quantity)),
+                Ordering::Equal => None,
+            },
+        };
+        let is_better = match &best {
+            None => true,
+            Some(best) => {
+                if candidate.matched_quantity != best.matched_quantity {
+                    candidate.matched_quantity > best.matched_quantity
+                } else if imbalance_quantity(&candidate) != imbalance_quantity(best) {
+                    // `None` (no imbalance) orders before any quantity
+                    imbalance_quantity(&candidate) < imbalance_quantity(best)
+                } else if let Some(reference_price) = reference_price {
+                    distance(price, reference_price) < distance(best.price, reference_price)
+                } else {
+                    false
+                }
+            }
+        };
+        if is_better {
+            best = Some(candidate);
+        }
+    }
+    best
+}
+
+fn imbalance_quantity<P: Price, Q: Quantity>(clearing: &AuctionClearing<P, Q>) -> Option<Q> {
+    clearing.imbalance.map(|(_, quantity)| quantity)
+}
+
+/// the running sum of the quantities of the `orders` crossing each of the `prices`, both
+/// walked in the same direction. `None` while no order crosses the price.
+fn cumulative_quantities<'a, P: Price + 'a, Q: Quantity + 'a>(
+    prices: impl Iterator<Item = &'a P>,
+    orders: impl Iterator<Item = &'a (P, Q)>,
+    crosses: impl Fn(P, P) -> bool,
+) -> Vec<Option<Q>> {
+    let mut orders = orders.peekable();
+    let mut sum = None;
+    prices
+        .map(|price| {
+            while let Some((_, quantity)) = orders.next_if(|(p, _)| crosses(*p, *price)) {
+                sum = Some(match sum {
+                    Some(sum) => sum + *quantity,
+                    None => *quantity,
+                });
+            }
+            sum
+        })
+        .collect()
+}
+
+fn distance<P: Price>(a: P, b: P) -> P {
+    if a > b {
+        a - b
+    } else {
+        b - a
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clearing_price_maximises_the_matched_quantity() {
+        let clearing = clearing_price(&[(100u64, 10u64)], &[(99, 6), (100, 6)], None).unwrap();
+        assert_eq!(clearing.price, 100);
+        assert_eq!(clearing.matched_quantity, 10);
+        assert_eq!(clearing.imbalance, Some((OrderSide::Ask, 2)));
+    }
+
+    #[test]
+    fn clearing_price_breaks_ties_by_the_minimum_imbalance() {
+        // 10 is matched at both 100 and 101, with 4 left on the bid side at 100
+        let clearing = clearing_price(&[(101u64, 10u64), (100, 4)], &[(100, 10)], None).unwrap();
+        assert_eq!(clearing.price, 101);
+        assert_eq!(clearing.matched_quantity, 10);
+        assert_eq!(clearing.imbalance, None);
+    }
+
+    #[test]
+    fn clearing_price_breaks_ties_by_the_distance_to_the_reference_price() {
+        let bid_orders = [(102u64, 10u64)];
+        let ask_orders = [(100, 10)];
+        assert_eq!(
+            clearing_price(&bid_orders, &ask_orders, Some(102))
+                .unwrap()
+                .price,
+            102
+        );
+        assert_eq!(
+            clearing_price(&bid_orders, &ask_orders, Some(99))
+                .unwrap()
+                .price,
+            100
+        );
+        // the lowest price without a reference price
+        assert_eq!(
+            clearing_price(&bid_orders, &ask_orders, None)
+                .unwrap()
+                .price,
+            100
+        );
+    }
+
+    #[test]
+    fn clearing_price_accumulates_the_orders_in_any_order() {
+        let bid_orders = [(100u64, 4u64), (102, 3), (100, 2)];
+        let ask_orders = [(101, 5), (99, 1)];
+        let clearing = clearing_price(&bid_orders, &ask_orders, None).unwrap();
+        assert_eq!(clearing.price, 101);
+        assert_eq!(clearing.matched_quantity, 3);
+        assert_eq!(clearing.imbalance, Some((OrderSide::Ask, 3)));
+    }
+
+    #[test]
+    fn clearing_price_is_none_for_an_uncrossed_book() {
+        assert!(clearing_price(&[(99u64, 5u64)], &[(100, 5)], None).is_none());
+    }
+}
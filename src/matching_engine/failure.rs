@@ -2,7 +2,22 @@ use crate::OrderId;
 
 #[derive(Debug)]
 pub enum MatchingEngineFailure<ID: OrderId> {
-    OrderNotFound { order_id: ID, target_order_id: ID },
+    OrderNotFound {
+        order_id: ID,
+        target_order_id: ID,
+    },
     FailedToEnqueueOrder(ID),
     NoMatch(ID),
+    WouldTakeLiquidity(ID),
+    /// all-or-none or minimum quantity could not be filled
+    ExecutionConstraintNotMet(ID),
+    /// no price in the book to peg the order to
+    NoReferencePrice(ID),
+    /// the request is not accepted in the current session state
+    NotAllowedInSession(ID),
+    /// the amended quantity is less than the quantity already filled
+    AmendBelowFilledQuantity {
+        order_id: ID,
+        target_order_id: ID,
+    },
 }
@@ -0,0 +1,91 @@
+use super::is_self_trade;
+use crate::{
+    LimitOrderOf, LimitOrderRepositoryLike, MarketOrderQuantity, Notional, OrderSide,
+    SelfTradePrevention,
+};
+
+/// walks the opposite order book without modifying it and verifies that
+/// the given quantity could be filled by the orders crossing the limit price.
+/// `limit_price` is `None` for market orders.
+/// an order of the same `owner` is skipped when the self-trade prevention cancels the resting
+/// order and lets the matching go on, and ends the walk when it cancels or decrements the
+/// incoming order. with `level_wide_self_trade`, as under pro-rata matching, the self-trade
+/// prevention applies as soon as the walk reaches a price level holding an order of the same owner.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn has_enough_liquidity<R: LimitOrderRepositoryLike>(
+    opposite_repository: &R,
+    tx: &mut R::Transaction,
+    side: OrderSide,
+    owner: Option<u64>,
+    limit_price: Option<R::Price>,
+    quantity: MarketOrderQuantity<R::Quantity>,
+    self_trade_prevention: SelfTradePrevention,
+    level_wide_self_trade: bool,
+    now_ms: u64,
+) -> Result<bool, R::Err>
+where
+    R::Quantity: Notional<R::Price>,
+{
+    let (mut remaining, is_quote) = match quantity {
+        MarketOrderQuantity::Base(quantity) => (quantity, false),
+        MarketOrderQuantity::Quote(quantity) => (quantity, true),
+    };
+    let stops_matching = self_trade_prevention != SelfTradePrevention::CancelOldest;
+    let mut level = None;
+    let mut opposite_order = opposite_repository.next(tx)?;
+    while let Some(order) = opposite_order {
+        let could_be_matched = match (side, limit_price) {
+            (_, None) => true,
+            (OrderSide::Bid, Some(price)) => price >= order.price,
+            (OrderSide::Ask, Some(price)) => price <= order.price,
+        };
+        if !could_be_matched {
+            break;
+        }
+        if level_wide_self_trade && stops_matching && level != Some((order.price, order.hidden)) {
+            level = Some((order.price, order.hidden));
+            if has_self_trade_at_level(opposite_repository, tx, owner, &order, now_ms)? {
+                return Ok(false);
+            }
+        }
+        let order_quantity = if is_quote {
+            order.remaining_quantity().notional(order.price)
+        } else {
+            order.remaining_quantity()
+        };
+        if order.is_expired(now_ms) {
+            // expired orders are never matched
+        } else if is_self_trade(owner, &order) {
+            if stops_matching {
+                return Ok(false);
+            }
+        } else if order_quantity >= remaining {
+            return Ok(true);
+        } else {
+            remaining = remaining - order_quantity;
+        }
+        opposite_order = opposite_repository.next_after(tx, &order)?;
+    }
+    Ok(false)
+}
+
+/// `true` if the price level starting at `order` holds an unexpired order of the given owner.
+fn has_self_trade_at_level<R: LimitOrderRepositoryLike>(
+    opposite_repository: &R,
+    tx: &mut R::Transaction,
+    owner: Option<u64>,
+    order: &LimitOrderOf<R>,
+    now_ms: u64,
+) -> Result<bool, R::Err> {
+    let mut level_order = Some(order.clone());
+    while let Some(order_at_level) = level_order {
+        if order_at_level.price != order.price || order_at_level.hidden != order.hidden {
+            break;
+        }
+        if !order_at_level.is_expired(now_ms) && is_self_trade(owner, &order_at_level) {
+            return Ok(true);
+        }
+        level_order = opposite_repository.next_after(tx, &order_at_level)?;
+    }
+    Ok(false)
+}
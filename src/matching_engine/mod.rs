@@ -1,27 +1,63 @@
+mod allocation;
+mod auction;
 mod failure;
+mod liquidity;
 mod output;
+#[cfg(test)]
+mod tests;
 
 pub use failure::*;
 pub use output::*;
 
-pub type OrderProcessingResult<ID, P, Q> =
-    Vec<Result<MatchingEngineOutput<ID, P, Q>, MatchingEngineFailure<ID>>>;
+pub type OrderProcessingOutput<ID, P, Q> =
+    Result<MatchingEngineOutput<ID, P, Q>, MatchingEngineFailure<ID>>;
+
+pub type OrderProcessingResult<ID, P, Q> = Vec<OrderProcessingOutput<ID, P, Q>>;
+
+/// the limit order of the engine `E`.
+pub type EngineLimitOrder<E> = LimitOrder<
+    <E as MatchingEngine>::OrderId,
+    <E as MatchingEngine>::Asset,
+    <E as MatchingEngine>::Price,
+    <E as MatchingEngine>::Quantity,
+>;
+
+/// the clearing of a call auction of the engine `E`.
+pub type EngineAuctionClearing<E> =
+    AuctionClearing<<E as MatchingEngine>::Price, <E as MatchingEngine>::Quantity>;
+
+/// the outputs of the engine `E`.
+pub type EngineProcessingResult<E> = OrderProcessingResult<
+    <E as MatchingEngine>::OrderId,
+    <E as MatchingEngine>::Price,
+    <E as MatchingEngine>::Quantity,
+>;
 
 use crate::{
     model::{
-        AmendOrder, Asset, AssetPair, CancelOrder, LimitOrder, MarketOrder, OrderId, OrderRequest,
-        OrderSide, OrderType, Price, Quantity,
+        checked_sub, Allocation, AmendOrder, Asset, AssetPair, AuctionClearing, CancelOrder,
+        ChildOrders, ExecutionConstraint, LimitOrder, MarketOrder, MarketOrderQuantity,
+        MatchingPolicy, Notional, OcoOrder, OrderId, OrderLink, OrderRequest, OrderSide, OrderType,
+        ParticipantRole, PegReference, PostOnly, Price, Quantity, SelfTradePrevention,
+        SessionState, StopOrder, TimeInForce, TradingScheduleLike,
     },
-    repository::LimitOrderRepositoryLike,
+    repository::{
+        ChildOrderRepositoryLike, LimitOrderRepositoryLike, OrderLinkRepositoryLike,
+        StopOrderRepositoryLike,
+    },
+};
+use std::{
+    cmp::Ordering,
+    time::{SystemTime, UNIX_EPOCH},
 };
-use std::time::{SystemTime, UNIX_EPOCH};
 
 pub trait MatchingEngine {
     type Err;
     type Asset: Asset;
     type OrderId: OrderId;
     type Price: Price;
-    type Quantity: Quantity;
+    /// `Default` is the zero quantity.
+    type Quantity: Quantity + Notional<Self::Price> + Allocation + Default;
     type Transaction;
     type BidLimitOrderRepository: LimitOrderRepositoryLike<
         Err = Self::Err,
@@ -39,82 +75,735 @@ pub trait MatchingEngine {
         Quantity = Self::Quantity,
         Transaction = Self::Transaction,
     >;
+    type StopOrderRepository: StopOrderRepositoryLike<
+        Err = Self::Err,
+        Asset = Self::Asset,
+        OrderId = Self::OrderId,
+        Price = Self::Price,
+        Quantity = Self::Quantity,
+        Transaction = Self::Transaction,
+    >;
+    type OrderLinkRepository: OrderLinkRepositoryLike<
+        Err = Self::Err,
+        OrderId = Self::OrderId,
+        Transaction = Self::Transaction,
+    >;
+    type ChildOrderRepository: ChildOrderRepositoryLike<
+        Err = Self::Err,
+        Asset = Self::Asset,
+        OrderId = Self::OrderId,
+        Price = Self::Price,
+        Quantity = Self::Quantity,
+        Transaction = Self::Transaction,
+    >;
+    type TradingSchedule: TradingScheduleLike;
 
     fn asset_pair(&self) -> &AssetPair<Self::Asset>;
     fn bid_limit_order_repository(&self) -> &Self::BidLimitOrderRepository;
     fn ask_limit_order_repository(&self) -> &Self::AskLimitOrderRepository;
+    fn stop_order_repository(&self) -> &Self::StopOrderRepository;
+    fn order_link_repository(&self) -> &Self::OrderLinkRepository;
+    fn child_order_repository(&self) -> &Self::ChildOrderRepository;
+    fn trading_schedule(&self) -> &Self::TradingSchedule;
+    fn tick_size(&self) -> Self::Price;
     fn current_timestamp_ms(&self) -> u64 {
         let now = SystemTime::now();
         let since_the_epoch = now.duration_since(UNIX_EPOCH).unwrap();
         since_the_epoch.as_millis() as u64
     }
 
+    fn session_state(&self) -> SessionState {
+        self.trading_schedule()
+            .session_state(self.current_timestamp_ms())
+    }
+
+    /// allocation of the incoming quantity across the orders at the best price.
+    fn matching_policy(&self) -> MatchingPolicy<Self::Quantity> {
+        MatchingPolicy::Fifo
+    }
+
+    /// role of the owner at the venue, such as a lead market maker receiving priority
+    /// allocations under `MatchingPolicy::TopOrderProRata`. the engine assigns it to the orders
+    /// resting in the book, whatever role the order requests carry.
+    fn participant_role(&self, _owner: Option<u64>) -> ParticipantRole {
+        ParticipantRole::Regular
+    }
+
+    /// action taken when an incoming order meets a resting order of the same owner.
+    fn self_trade_prevention(&self) -> SelfTradePrevention {
+        SelfTradePrevention::CancelNewest
+    }
+
+    /// price breaking the ties of the auction clearing prices, such as the last trade price.
+    fn auction_reference_price(&self) -> Option<Self::Price> {
+        None
+    }
+
     fn process_order(
         &mut self,
         tx: &mut Self::Transaction,
         order_request: OrderRequest<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
-    ) -> Result<OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity>, Self::Err> {
+    ) -> Result<EngineProcessingResult<Self>, Self::Err> {
         let mut proc_result: OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity> =
             vec![];
+        let peg_reference = self.peg_reference(tx)?;
+        self.process_order_request(tx, &mut proc_result, order_request)?;
+        // the child orders of the filled entry orders are submitted once the matching is complete
+        self.process_child_orders(tx, &mut proc_result, 0)?;
+        self.process_pegged_orders(tx, &mut proc_result, &peg_reference)?;
+        self.publish_indicative_auction(tx, &mut proc_result)?;
+        Ok(proc_result)
+    }
+
+    /// processes the order request into `results`, leaving the child orders of the filled
+    /// entry orders to the caller.
+    fn process_order_request(
+        &mut self,
+        tx: &mut Self::Transaction,
+        results: &mut OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity>,
+        order_request: OrderRequest<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+    ) -> Result<(), Self::Err> {
+        let from = results.len();
+        let session_state = self.session_state();
+        let order_request = match order_request {
+            // DAY orders expire at the close of the session
+            OrderRequest::Limit(limit_order) if limit_order.time_in_force == TimeInForce::DAY => {
+                let session_close_time_ms = self
+                    .trading_schedule()
+                    .session_close_time_ms(self.current_timestamp_ms());
+                OrderRequest::Limit(limit_order.expiring_at(session_close_time_ms))
+            }
+            order_request => order_request,
+        };
         match order_request {
+            order_request if !order_request.is_allowed_in(session_state) => {
+                results.push(Err(MatchingEngineFailure::NotAllowedInSession(
+                    order_request.id(),
+                )));
+            }
+            OrderRequest::Limit(limit_order) if session_state.collects_orders() => {
+                assert_eq!(*self.asset_pair(), limit_order.asset_pair);
+                // collect the order without matching
+                results.push(Ok(MatchingEngineOutput::Accepted {
+                    id: limit_order.id,
+                    order_type: limit_order.order_type(),
+                    timestamp_ms: self.current_timestamp_ms(),
+                }));
+                self.process_unmatched_limit_order(tx, results, &limit_order)?;
+            }
+            OrderRequest::Market(market_order)
+                if !self.market_order_meets_execution_constraint(tx, &market_order)? =>
+            {
+                assert_eq!(*self.asset_pair(), market_order.asset_pair);
+                results.push(Err(MatchingEngineFailure::ExecutionConstraintNotMet(
+                    market_order.id,
+                )));
+            }
             OrderRequest::Market(market_order) => {
                 assert_eq!(*self.asset_pair(), market_order.asset_pair);
-                proc_result.push(Ok(MatchingEngineOutput::Accepted {
+                results.push(Ok(MatchingEngineOutput::Accepted {
                     id: market_order.id,
-                    order_type: OrderType::Market,
+                    order_type: market_order.order_type(),
                     timestamp_ms: self.current_timestamp_ms(),
                 }));
-                self.process_market_order(tx, &mut proc_result, &market_order)?;
+                if market_order.market_to_limit {
+                    self.process_market_to_limit_order(tx, results, &market_order)?;
+                } else {
+                    self.process_market_order(tx, results, &market_order, None)?;
+                }
+                self.process_triggered_orders(tx, results, from)?;
+            }
+            OrderRequest::Limit(limit_order) if limit_order.post_only.is_some() => {
+                assert_eq!(*self.asset_pair(), limit_order.asset_pair);
+                self.process_limit_maker_order(tx, results, &limit_order)?;
+            }
+            OrderRequest::Limit(limit_order) if limit_order.peg.is_some() => {
+                assert_eq!(*self.asset_pair(), limit_order.asset_pair);
+                self.process_pegged_order(tx, results, &limit_order)?;
+            }
+            OrderRequest::Limit(limit_order)
+                if !self.meets_execution_constraint(
+                    tx,
+                    limit_order.side,
+                    limit_order.owner,
+                    Some(limit_order.price),
+                    MarketOrderQuantity::Base(limit_order.quantity),
+                    limit_order.execution_constraint,
+                )? =>
+            {
+                assert_eq!(*self.asset_pair(), limit_order.asset_pair);
+                results.push(Err(MatchingEngineFailure::ExecutionConstraintNotMet(
+                    limit_order.id,
+                )));
+                self.release_order(tx, results, &limit_order.id)?;
             }
             OrderRequest::Limit(limit_order) => {
                 assert_eq!(*self.asset_pair(), limit_order.asset_pair);
-                proc_result.push(Ok(MatchingEngineOutput::Accepted {
+                results.push(Ok(MatchingEngineOutput::Accepted {
                     id: limit_order.id,
                     order_type: OrderType::Limit,
                     timestamp_ms: self.current_timestamp_ms(),
                 }));
-                self.process_limit_order(tx, &mut proc_result, &limit_order)?;
+                if limit_order.time_in_force == TimeInForce::FOK
+                    && !self.has_enough_liquidity(
+                        tx,
+                        limit_order.side,
+                        limit_order.owner,
+                        Some(limit_order.price),
+                        MarketOrderQuantity::Base(limit_order.quantity),
+                    )?
+                {
+                    // kill the order before touching the book
+                    self.process_unmatched_limit_order(tx, results, &limit_order)?;
+                } else {
+                    self.process_limit_order(tx, results, &limit_order)?;
+                }
+                self.process_triggered_orders(tx, results, from)?;
+            }
+            OrderRequest::Stop(stop_order) => {
+                assert_eq!(*self.asset_pair(), stop_order.asset_pair);
+                results.push(Ok(MatchingEngineOutput::Accepted {
+                    id: stop_order.id,
+                    order_type: stop_order.order_type,
+                    timestamp_ms: self.current_timestamp_ms(),
+                }));
+                self.stop_order_repository().create(tx, &stop_order)?;
+            }
+            OrderRequest::Oco(oco_order) => {
+                assert_eq!(*self.asset_pair(), oco_order.limit_order.asset_pair);
+                assert_eq!(*self.asset_pair(), oco_order.stop_order.asset_pair);
+                assert_eq!(oco_order.limit_order.side, oco_order.stop_order.side);
+                let (limit_order_link, stop_order_link) = oco_order.links();
+                self.order_link_repository().create(tx, &limit_order_link)?;
+                self.order_link_repository().create(tx, &stop_order_link)?;
+                results.push(Ok(MatchingEngineOutput::Accepted {
+                    id: oco_order.stop_order.id,
+                    order_type: oco_order.stop_order.order_type,
+                    timestamp_ms: self.current_timestamp_ms(),
+                }));
+                self.stop_order_repository()
+                    .create(tx, &oco_order.stop_order)?;
+                // the limit order may trade right away and cancel the stop order
+                self.process_order_request(
+                    tx,
+                    results,
+                    OrderRequest::Limit(oco_order.limit_order),
+                )?;
+            }
+            OrderRequest::Bracket(bracket_order) => {
+                assert_eq!(*self.asset_pair(), bracket_order.entry_order.asset_pair);
+                assert_eq!(
+                    *self.asset_pair(),
+                    bracket_order.take_profit_order.asset_pair
+                );
+                assert_eq!(*self.asset_pair(), bracket_order.stop_loss_order.asset_pair);
+                let exit_side = bracket_order.entry_order.side.opposite();
+                assert_eq!(exit_side, bracket_order.take_profit_order.side);
+                assert_eq!(exit_side, bracket_order.stop_loss_order.side);
+                self.child_order_repository()
+                    .create(tx, &bracket_order.child_orders())?;
+                // the child orders are submitted on the fills of the entry order
+                self.process_order_request(
+                    tx,
+                    results,
+                    OrderRequest::Limit(bracket_order.entry_order),
+                )?;
             }
             OrderRequest::Amend(amend_order) => {
                 let is_amendable = match amend_order.target_order_type {
                     OrderType::Market => false,
-                    OrderType::Limit => true,
+                    // the remainder of a market-to-limit order rests as a limit order
+                    OrderType::Limit | OrderType::LimitMaker | OrderType::MarketToLimit => true,
+                    OrderType::StopLoss
+                    | OrderType::StopLossLimit
+                    | OrderType::TakeProfit
+                    | OrderType::TakeProfitLimit
+                    | OrderType::TrailingStop => true,
                 };
                 assert!(is_amendable);
-                self.process_amend_order(tx, &mut proc_result, &amend_order)?;
+                self.process_amend_order(tx, results, &amend_order)?;
+                self.process_triggered_orders(tx, results, from)?;
             }
             OrderRequest::Cancel(cancel_order) => {
                 let is_cancelable = match cancel_order.target_order_type {
                     OrderType::Market => false,
-                    OrderType::Limit => true,
+                    // the remainder of a market-to-limit order rests as a limit order
+                    OrderType::Limit | OrderType::LimitMaker | OrderType::MarketToLimit => true,
+                    OrderType::StopLoss
+                    | OrderType::StopLossLimit
+                    | OrderType::TakeProfit
+                    | OrderType::TakeProfitLimit
+                    | OrderType::TrailingStop => true,
                 };
                 assert!(is_cancelable);
-                self.process_cancel_order(tx, &mut proc_result, &cancel_order)?;
+                self.process_cancel_order(tx, results, &cancel_order)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// whether the opposite orders crossing `limit_price` could fill the quantity.
+    /// the orders of the same `owner` are not counted as they never trade with the order.
+    fn has_enough_liquidity(
+        &self,
+        tx: &mut Self::Transaction,
+        side: OrderSide,
+        owner: Option<u64>,
+        limit_price: Option<Self::Price>,
+        quantity: MarketOrderQuantity<Self::Quantity>,
+    ) -> Result<bool, Self::Err> {
+        // a pro-rata price level is allocated at once
+        let level_wide_self_trade = !matches!(self.matching_policy(), MatchingPolicy::Fifo);
+        match side {
+            OrderSide::Bid => liquidity::has_enough_liquidity(
+                self.ask_limit_order_repository(),
+                tx,
+                side,
+                owner,
+                limit_price,
+                quantity,
+                self.self_trade_prevention(),
+                level_wide_self_trade,
+                self.current_timestamp_ms(),
+            ),
+            OrderSide::Ask => liquidity::has_enough_liquidity(
+                self.bid_limit_order_repository(),
+                tx,
+                side,
+                owner,
+                limit_price,
+                quantity,
+                self.self_trade_prevention(),
+                level_wide_self_trade,
+                self.current_timestamp_ms(),
+            ),
+        }
+    }
+
+    /// whether the opposite orders could fill the quantity required by the execution constraint
+    /// in this matching pass. orders without a constraint always meet it.
+    fn meets_execution_constraint(
+        &self,
+        tx: &mut Self::Transaction,
+        side: OrderSide,
+        owner: Option<u64>,
+        limit_price: Option<Self::Price>,
+        quantity: MarketOrderQuantity<Self::Quantity>,
+        execution_constraint: Option<ExecutionConstraint<Self::Quantity>>,
+    ) -> Result<bool, Self::Err> {
+        let execution_constraint = match execution_constraint {
+            Some(execution_constraint) => execution_constraint,
+            None => return Ok(true),
+        };
+        let required_quantity = match quantity {
+            MarketOrderQuantity::Base(quantity) => {
+                MarketOrderQuantity::Base(execution_constraint.required_quantity(quantity))
+            }
+            MarketOrderQuantity::Quote(quantity) => {
+                MarketOrderQuantity::Quote(execution_constraint.required_quantity(quantity))
+            }
+        };
+        self.has_enough_liquidity(tx, side, owner, limit_price, required_quantity)
+    }
+
+    /// `meets_execution_constraint` for a market order, which trades no further than the worst
+    /// price allowed by its price protection from the best opposite order it meets first.
+    fn market_order_meets_execution_constraint(
+        &self,
+        tx: &mut Self::Transaction,
+        market_order: &MarketOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+    ) -> Result<bool, Self::Err> {
+        let limit_price = match (
+            market_order.execution_constraint,
+            market_order.price_protection,
+        ) {
+            (Some(_), Some(price_protection)) => self
+                .best_order(tx, market_order.side.opposite(), |order| {
+                    !is_self_trade(market_order.owner, order)
+                })?
+                .and_then(|first_order| {
+                    price_protection.limit_price(market_order.side, first_order.price)
+                }),
+            _ => None,
+        };
+        self.meets_execution_constraint(
+            tx,
+            market_order.side,
+            market_order.owner,
+            limit_price,
+            market_order.quantity,
+            market_order.execution_constraint,
+        )
+    }
+
+    /// returns the best opposite limit order, removing the expired orders found on the way.
+    fn next_opposite_order(
+        &mut self,
+        tx: &mut Self::Transaction,
+        results: &mut OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity>,
+        side: OrderSide,
+    ) -> Result<Option<EngineLimitOrder<Self>>, Self::Err> {
+        loop {
+            let opposite_order = match side {
+                OrderSide::Bid => self.ask_limit_order_repository().next(tx),
+                OrderSide::Ask => self.bid_limit_order_repository().next(tx),
+            }?;
+            match opposite_order {
+                Some(opposite_order)
+                    if self.has_expired(&opposite_order, self.current_timestamp_ms()) =>
+                {
+                    self.expire_limit_order(tx, results, &opposite_order)?;
+                }
+                _ => return Ok(opposite_order),
+            }
+        }
+    }
+
+    /// whether the resting order has expired at `now_ms`. the DAY orders expire with the
+    /// session, even before their expire time when the session is closed early.
+    fn has_expired(
+        &self,
+        limit_order: &LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+        now_ms: u64,
+    ) -> bool {
+        limit_order.is_expired(now_ms)
+            || (limit_order.time_in_force == TimeInForce::DAY
+                && self.trading_schedule().session_state(now_ms) == SessionState::Closed)
+    }
+
+    /// removes the good-till-date orders expired at `now_ms` from the order book, and the DAY
+    /// orders once the session is closed.
+    fn expire_orders(
+        &mut self,
+        tx: &mut Self::Transaction,
+        now_ms: u64,
+    ) -> Result<EngineProcessingResult<Self>, Self::Err> {
+        let mut proc_result: OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity> =
+            vec![];
+        let mut expired_orders = vec![];
+        let mut order = self.bid_limit_order_repository().next(tx)?;
+        while let Some(bid_order) = order {
+            order = self
+                .bid_limit_order_repository()
+                .next_after(tx, &bid_order)?;
+            if self.has_expired(&bid_order, now_ms) {
+                expired_orders.push(bid_order);
+            }
+        }
+        let mut order = self.ask_limit_order_repository().next(tx)?;
+        while let Some(ask_order) = order {
+            order = self
+                .ask_limit_order_repository()
+                .next_after(tx, &ask_order)?;
+            if self.has_expired(&ask_order, now_ms) {
+                expired_orders.push(ask_order);
             }
         }
+        for expired_order in expired_orders {
+            self.expire_limit_order(tx, &mut proc_result, &expired_order)?;
+        }
+        // the expired orders have left the prices followed by the pegged orders before
+        // their removal
+        self.reprice_pegged_orders(tx, &mut proc_result)?;
+        self.publish_indicative_auction(tx, &mut proc_result)?;
         Ok(proc_result)
     }
 
-    fn process_market_order(
+    /// executes the orders collected during the call phase of an auction at the single
+    /// clearing price (see `auction::clearing_price`). the orders of the same owner are
+    /// subject to the self-trade prevention. the unmatched orders stay in the order book for
+    /// continuous trading.
+    fn uncross(
+        &mut self,
+        tx: &mut Self::Transaction,
+    ) -> Result<EngineProcessingResult<Self>, Self::Err> {
+        let mut proc_result: OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity> =
+            vec![];
+        let peg_reference = self.peg_reference(tx)?;
+        // the stop orders triggered by the fills are collected in a call phase and may cross
+        // the book again
+        while let Some(clearing) = self.indicative_auction_clearing(tx)? {
+            let from = proc_result.len();
+            let price = clearing.price;
+            loop {
+                let bid_order = self.next_opposite_order(tx, &mut proc_result, OrderSide::Ask)?;
+                let ask_order = self.next_opposite_order(tx, &mut proc_result, OrderSide::Bid)?;
+                match (bid_order, ask_order) {
+                    (Some(bid_order), Some(ask_order))
+                        if bid_order.price >= price && ask_order.price <= price =>
+                    {
+                        if is_self_trade(bid_order.owner, &ask_order) {
+                            self.prevent_auction_self_trade(
+                                tx,
+                                &mut proc_result,
+                                &bid_order,
+                                &ask_order,
+                            )?;
+                        } else {
+                            self.match_auction_orders(
+                                tx,
+                                &mut proc_result,
+                                &bid_order,
+                                &ask_order,
+                                price,
+                            )?;
+                        }
+                    }
+                    _ => break,
+                }
+            }
+            self.process_triggered_orders(tx, &mut proc_result, from)?;
+        }
+        self.process_child_orders(tx, &mut proc_result, 0)?;
+        self.process_pegged_orders(tx, &mut proc_result, &peg_reference)?;
+        Ok(proc_result)
+    }
+
+    /// computes the clearing price, the matched quantity and the imbalance of an uncross of
+    /// the current order book without executing it. `None` if the book is not crossed.
+    fn indicative_auction_clearing(
+        &self,
+        tx: &mut Self::Transaction,
+    ) -> Result<Option<EngineAuctionClearing<Self>>, Self::Err> {
+        let quantities = |orders: Vec<EngineLimitOrder<Self>>| -> Vec<_> {
+            orders
+                .iter()
+                .map(|order| (order.price, order.remaining_quantity()))
+                .collect()
+        };
+        let bid_orders = quantities(self.book_orders(tx, OrderSide::Bid)?);
+        let ask_orders = quantities(self.book_orders(tx, OrderSide::Ask)?);
+        Ok(auction::clearing_price(
+            &bid_orders,
+            &ask_orders,
+            self.auction_reference_price(),
+        ))
+    }
+
+    /// publishes the indicative clearing of the call auction after the order book has changed.
+    fn publish_indicative_auction(
+        &mut self,
+        tx: &mut Self::Transaction,
+        results: &mut OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity>,
+    ) -> Result<(), Self::Err> {
+        // only the latest clearing is published for the requests processed within this one
+        results
+            .retain(|result| !matches!(result, Ok(MatchingEngineOutput::IndicativeAuction { .. })));
+        let book_changed = results.iter().any(|result| result.is_ok());
+        if book_changed && self.session_state().collects_orders() {
+            results.push(Ok(MatchingEngineOutput::IndicativeAuction {
+                clearing: self.indicative_auction_clearing(tx)?,
+                timestamp_ms: self.current_timestamp_ms(),
+            }));
+        }
+        Ok(())
+    }
+
+    /// the unexpired orders on the given side in priority order.
+    fn book_orders(
+        &self,
+        tx: &mut Self::Transaction,
+        side: OrderSide,
+    ) -> Result<Vec<EngineLimitOrder<Self>>, Self::Err> {
+        let now_ms = self.current_timestamp_ms();
+        let mut orders = vec![];
+        let mut order = match side {
+            OrderSide::Bid => self.bid_limit_order_repository().next(tx)?,
+            OrderSide::Ask => self.ask_limit_order_repository().next(tx)?,
+        };
+        while let Some(limit_order) = order {
+            order = match side {
+                OrderSide::Bid => self
+                    .bid_limit_order_repository()
+                    .next_after(tx, &limit_order)?,
+                OrderSide::Ask => self
+                    .ask_limit_order_repository()
+                    .next_after(tx, &limit_order)?,
+            };
+            if !self.has_expired(&limit_order, now_ms) {
+                orders.push(limit_order);
+            }
+        }
+        Ok(orders)
+    }
+
+    fn expire_limit_order(
         &mut self,
         tx: &mut Self::Transaction,
         results: &mut OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity>,
-        market_order: &MarketOrder<Self::OrderId, Self::Asset, Self::Quantity>,
+        limit_order: &LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
     ) -> Result<(), Self::Err> {
-        let opposite_order = match market_order.side {
-            OrderSide::Bid => self.ask_limit_order_repository().next(tx),
-            OrderSide::Ask => self.bid_limit_order_repository().next(tx),
+        match limit_order.side {
+            OrderSide::Bid => self
+                .bid_limit_order_repository()
+                .delete_by_order_id(tx, &limit_order.id),
+            OrderSide::Ask => self
+                .ask_limit_order_repository()
+                .delete_by_order_id(tx, &limit_order.id),
         }?;
+        results.push(Ok(MatchingEngineOutput::Expired {
+            id: limit_order.id,
+            side: limit_order.side,
+            order_type: limit_order.order_type(),
+            quantity: limit_order.remaining_quantity(),
+            timestamp_ms: self.current_timestamp_ms(),
+        }));
+        self.release_order(tx, results, &limit_order.id)?;
+        Ok(())
+    }
+
+    /// `first_price` is the price of the first fill of the order, `None` until it has traded.
+    fn process_market_order(
+        &mut self,
+        tx: &mut Self::Transaction,
+        results: &mut OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity>,
+        market_order: &MarketOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+        first_price: Option<Self::Price>,
+    ) -> Result<(), Self::Err> {
+        let opposite_order = self.next_opposite_order(tx, results, market_order.side)?;
         if let Some(opposite_order) = opposite_order {
-            let matching_complete = self.match_market_order_with_limit_order(
-                tx,
-                results,
-                &market_order,
-                &opposite_order,
-            )?;
-            let next_market_order = market_order.sub_quantity(opposite_order.quantity);
-            if !matching_complete {
-                self.process_market_order(tx, results, &next_market_order)?;
+            let first_fill_price = first_price.unwrap_or(opposite_order.price);
+            let slipped = match market_order.price_protection {
+                Some(price_protection) => price_protection.is_exceeded_by(
+                    market_order.side,
+                    first_fill_price,
+                    opposite_order.price,
+                ),
+                None => false,
+            };
+            // base quantity of the market order at the price of the opposite order
+            let quantity = match market_order.quantity {
+                MarketOrderQuantity::Base(quantity) => Some(quantity),
+                MarketOrderQuantity::Quote(quote_quantity) => {
+                    Self::Quantity::from_notional(quote_quantity, opposite_order.price)
+                }
+            };
+            let self_trade_order = match quantity {
+                Some(_) if !slipped => {
+                    self.self_trade_order(tx, market_order.owner, &opposite_order)?
+                }
+                _ => None,
+            };
+            match (quantity, self_trade_order) {
+                (Some(quantity), Some(self_trade_order)) => {
+                    let cancelled_quantity = self.prevent_self_trade(
+                        tx,
+                        results,
+                        market_order.id,
+                        market_order.side,
+                        quantity,
+                        &self_trade_order,
+                    )?;
+                    let next_market_order = match cancelled_quantity {
+                        Some(cancelled_quantity) if cancelled_quantity >= quantity => {
+                            self.cancel_unspent_quote_quantity(
+                                results,
+                                market_order,
+                                quantity,
+                                opposite_order.price,
+                            );
+                            None
+                        }
+                        Some(cancelled_quantity) => Some(market_order.sub_quantity(
+                            cancelled_quantity,
+                            cancelled_quantity.notional(opposite_order.price),
+                        )),
+                        None => Some(market_order.clone()),
+                    };
+                    if let Some(next_market_order) = next_market_order {
+                        self.process_market_order(tx, results, &next_market_order, first_price)?;
+                    }
+                }
+                (Some(quantity), None) if !slipped => {
+                    let (matching_complete, traded_quantity) = match self.matching_policy() {
+                        MatchingPolicy::Fifo => (
+                            self.match_market_order_with_limit_order(
+                                tx,
+                                results,
+                                market_order,
+                                quantity,
+                                &opposite_order,
+                            )?,
+                            opposite_order.quantity,
+                        ),
+                        policy => self.match_order_with_price_level(
+                            tx,
+                            results,
+                            &market_order.to_limit_order(opposite_order.price, quantity),
+                            OrderType::Market,
+                            &opposite_order,
+                            policy,
+                        )?,
+                    };
+                    if !matching_complete {
+                        let next_market_order = market_order.sub_quantity(
+                            traded_quantity,
+                            traded_quantity.notional(opposite_order.price),
+                        );
+                        self.process_market_order(
+                            tx,
+                            results,
+                            &next_market_order,
+                            Some(first_fill_price),
+                        )?;
+                    } else {
+                        self.cancel_unspent_quote_quantity(
+                            results,
+                            market_order,
+                            quantity,
+                            opposite_order.price,
+                        );
+                    }
+                }
+                _ => {
+                    // beyond the price protection, or the rest of the quote quantity is less than a lot
+                    results.push(Ok(MatchingEngineOutput::RemainderCancelled {
+                        id: market_order.id,
+                        side: market_order.side,
+                        quantity: market_order.quantity,
+                        timestamp_ms: self.current_timestamp_ms(),
+                    }));
+                }
+            }
+        } else {
+            results.push(Err(MatchingEngineFailure::NoMatch(market_order.id)));
+        }
+        Ok(())
+    }
+
+    /// trades the market-to-limit order as a limit order at the price of the best opposite level,
+    /// so that the unfilled remainder rests in the book at the price of its first fill.
+    fn process_market_to_limit_order(
+        &mut self,
+        tx: &mut Self::Transaction,
+        results: &mut OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity>,
+        market_order: &MarketOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+    ) -> Result<(), Self::Err> {
+        let opposite_order = self.next_opposite_order(tx, results, market_order.side)?;
+        if let Some(opposite_order) = opposite_order {
+            let quantity = match market_order.quantity {
+                MarketOrderQuantity::Base(quantity) => Some(quantity),
+                MarketOrderQuantity::Quote(quote_quantity) => {
+                    Self::Quantity::from_notional(quote_quantity, opposite_order.price)
+                }
+            };
+            if let Some(quantity) = quantity {
+                let limit_order = market_order.to_limit_order(opposite_order.price, quantity);
+                self.process_limit_order(tx, results, &limit_order)?;
+                self.cancel_unspent_quote_quantity(
+                    results,
+                    market_order,
+                    quantity,
+                    opposite_order.price,
+                );
+            } else {
+                // the quote quantity is less than a lot
+                results.push(Ok(MatchingEngineOutput::RemainderCancelled {
+                    id: market_order.id,
+                    side: market_order.side,
+                    quantity: market_order.quantity,
+                    timestamp_ms: self.current_timestamp_ms(),
+                }));
             }
         } else {
             results.push(Err(MatchingEngineFailure::NoMatch(market_order.id)));
@@ -122,282 +811,986 @@ pub trait MatchingEngine {
         Ok(())
     }
 
+    /// reports the quote quantity of the market order left unspent once its base quantity
+    /// `quantity`, rounded down to a lot at `price`, has been matched or cancelled.
+    fn cancel_unspent_quote_quantity(
+        &self,
+        results: &mut OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity>,
+        market_order: &MarketOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+        quantity: Self::Quantity,
+        price: Self::Price,
+    ) {
+        if let MarketOrderQuantity::Quote(quote_quantity) = market_order.quantity {
+            let spent_quantity = quantity.notional(price);
+            if spent_quantity < quote_quantity {
+                results.push(Ok(MatchingEngineOutput::RemainderCancelled {
+                    id: market_order.id,
+                    side: market_order.side,
+                    quantity: MarketOrderQuantity::Quote(quote_quantity - spent_quantity),
+                    timestamp_ms: self.current_timestamp_ms(),
+                }));
+            }
+        }
+    }
+
     fn process_limit_order(
         &mut self,
         tx: &mut Self::Transaction,
         results: &mut OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity>,
         limit_order: &LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
     ) -> Result<(), Self::Err> {
-        let opposite_order = match limit_order.side {
-            OrderSide::Bid => self.ask_limit_order_repository().next(tx),
-            OrderSide::Ask => self.bid_limit_order_repository().next(tx),
-        }?;
+        let opposite_order = self.next_opposite_order(tx, results, limit_order.side)?;
         if let Some(opposite_order) = opposite_order {
             let could_be_matched = match limit_order.side {
                 // verify bid/ask price overlap
                 OrderSide::Bid => limit_order.price >= opposite_order.price,
                 OrderSide::Ask => limit_order.price <= opposite_order.price,
             };
-            if could_be_matched {
-                let matching_complete = self.match_limit_order_with_limit_order(
+            let self_trade_order = if could_be_matched {
+                self.self_trade_order(tx, limit_order.owner, &opposite_order)?
+            } else {
+                None
+            };
+            if let Some(self_trade_order) = self_trade_order {
+                let cancelled_quantity = self.prevent_self_trade(
                     tx,
                     results,
-                    &limit_order,
-                    &opposite_order,
+                    limit_order.id,
+                    limit_order.side,
+                    limit_order.quantity,
+                    &self_trade_order,
                 )?;
+                match cancelled_quantity {
+                    Some(cancelled_quantity) if cancelled_quantity >= limit_order.quantity => {}
+                    Some(cancelled_quantity) => {
+                        let next_limit_order = limit_order.sub_quantity(cancelled_quantity);
+                        self.process_limit_order(tx, results, &next_limit_order)?;
+                    }
+                    None => self.process_limit_order(tx, results, limit_order)?,
+                }
+            } else if could_be_matched {
+                let (matching_complete, traded_quantity) = match self.matching_policy() {
+                    MatchingPolicy::Fifo => (
+                        self.match_limit_order_with_limit_order(
+                            tx,
+                            results,
+                            limit_order,
+                            &opposite_order,
+                        )?,
+                        opposite_order.quantity,
+                    ),
+                    policy => self.match_order_with_price_level(
+                        tx,
+                        results,
+                        limit_order,
+                        limit_order.order_type(),
+                        &opposite_order,
+                        policy,
+                    )?,
+                };
                 if !matching_complete {
-                    let next_limit_order = limit_order.sub_quantity(opposite_order.quantity);
+                    let next_limit_order = limit_order.fill(traded_quantity);
                     self.process_limit_order(tx, results, &next_limit_order)?;
                 }
             } else {
-                self.store_new_limit_order(tx, results, &limit_order)?;
+                self.process_unmatched_limit_order(tx, results, limit_order)?;
             }
         } else {
-            self.store_new_limit_order(tx, results, &limit_order)?;
+            self.process_unmatched_limit_order(tx, results, limit_order)?;
         }
         Ok(())
     }
 
-    fn process_amend_order(
+    /// activates the stop orders triggered by the trades in `results[from..]`.
+    /// trades of the activated orders may move trailing stops and trigger further stop orders.
+    fn process_triggered_orders(
         &mut self,
         tx: &mut Self::Transaction,
         results: &mut OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity>,
-        amend_order: &AmendOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+        from: usize,
     ) -> Result<(), Self::Err> {
-        match amend_order.target_order_type {
-            OrderType::Limit => {
-                let order = match amend_order.side {
-                    OrderSide::Bid => self
-                        .bid_limit_order_repository()
-                        .get_by_order_id(tx, &amend_order.target_id),
-                    OrderSide::Ask => self
-                        .ask_limit_order_repository()
-                        .get_by_order_id(tx, &amend_order.target_id),
-                }?;
-                if let Some(mut target_order) = order {
-                    target_order.price = amend_order.price;
-                    target_order.quantity = amend_order.quantity;
-                    target_order.timestamp_ms = amend_order.timestamp_ms;
-                    match amend_order.side {
-                        OrderSide::Bid => {
-                            self.bid_limit_order_repository().update(tx, &target_order)
-                        }
-                        OrderSide::Ask => {
-                            self.ask_limit_order_repository().update(tx, &target_order)
-                        }
-                    }?;
-                    results.push(Ok(MatchingEngineOutput::Amended {
-                        id: amend_order.id,
-                        target_id: amend_order.target_id,
-                        price: amend_order.price,
-                        quantity: amend_order.quantity,
-                        timestamp_ms: self.current_timestamp_ms(),
-                    }));
-                    // TODO process limit order?
-                } else {
-                    results.push(Err(MatchingEngineFailure::OrderNotFound {
-                        order_id: amend_order.id,
-                        target_order_id: amend_order.target_id,
-                    }));
-                }
+        let mut from = from;
+        let mut last_price = None;
+        loop {
+            self.process_trailing_stop_orders(tx, results, from)?;
+            if let Some(price) = last_trade_price(&results[from..]) {
+                last_price = Some(price);
             }
-            _ => { /* ignore */ }
+            let stop_order = match last_price {
+                Some(last_price) => self
+                    .stop_order_repository()
+                    .next_triggered(tx, &last_price)?,
+                None => None,
+            };
+            let stop_order = match stop_order {
+                Some(stop_order) => stop_order,
+                None => return Ok(()),
+            };
+            self.stop_order_repository()
+                .delete_by_order_id(tx, &stop_order.id)?;
+            results.push(Ok(MatchingEngineOutput::Triggered {
+                id: stop_order.id,
+                side: stop_order.side,
+                order_type: stop_order.order_type,
+                stop_price: stop_order.stop_price,
+                timestamp_ms: self.current_timestamp_ms(),
+            }));
+            self.cancel_linked_order(tx, results, &stop_order.id)?;
+            from = results.len();
+            self.process_stop_order(tx, results, &stop_order)?;
         }
-        Ok(())
     }
 
-    fn process_cancel_order(
+    /// moves the stop prices of the trailing stop orders with the trades in `results[from..]`.
+    fn process_trailing_stop_orders(
         &mut self,
         tx: &mut Self::Transaction,
         results: &mut OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity>,
-        cancel_order: &CancelOrder<Self::OrderId, Self::Asset>,
+        from: usize,
     ) -> Result<(), Self::Err> {
-        match cancel_order.target_order_type {
-            OrderType::Limit => {
-                match cancel_order.side {
-                    OrderSide::Bid => self
-                        .bid_limit_order_repository()
-                        .delete_by_order_id(tx, &cancel_order.target_id),
-                    OrderSide::Ask => self
-                        .ask_limit_order_repository()
-                        .delete_by_order_id(tx, &cancel_order.target_id),
-                }?;
-                results.push(Ok(MatchingEngineOutput::Cancelled {
-                    id: cancel_order.id,
-                    target_id: cancel_order.target_id,
+        let (low, high) = match trade_price_range(&results[from..]) {
+            Some(range) => range,
+            None => return Ok(()),
+        };
+        for mut stop_order in self.stop_order_repository().get_trailing_stop_orders(tx)? {
+            let water_mark = stop_order.trailing_stop.and_then(|t| t.water_mark);
+            let moved = stop_order.trail(low, high);
+            if stop_order.trailing_stop.and_then(|t| t.water_mark) != water_mark {
+                // the water mark is persisted even if the stop price stays
+                self.stop_order_repository().update(tx, &stop_order)?;
+            }
+            if moved {
+                results.push(Ok(MatchingEngineOutput::TrailingStopMoved {
+                    id: stop_order.id,
+                    side: stop_order.side,
+                    stop_price: stop_order.stop_price,
                     timestamp_ms: self.current_timestamp_ms(),
                 }));
             }
-            _ => { /* ignore */ }
         }
         Ok(())
     }
 
-    fn store_new_limit_order(
+    fn process_stop_order(
         &mut self,
         tx: &mut Self::Transaction,
-        _results: &mut OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity>,
-        limit_order: &LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+        results: &mut OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity>,
+        stop_order: &StopOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
     ) -> Result<(), Self::Err> {
-        match limit_order.side {
-            OrderSide::Bid => self.bid_limit_order_repository().create(tx, limit_order),
-            OrderSide::Ask => self.ask_limit_order_repository().create(tx, limit_order),
+        let session_state = self.session_state();
+        match stop_order.price {
+            // no order is matched in a call phase
+            Some(price) if session_state.collects_orders() => {
+                self.process_unmatched_limit_order(tx, results, &stop_order.to_limit_order(price))
+            }
+            Some(price) => self.process_limit_order(tx, results, &stop_order.to_limit_order(price)),
+            None if session_state.collects_orders() => {
+                results.push(Err(MatchingEngineFailure::NotAllowedInSession(
+                    stop_order.id,
+                )));
+                self.release_order(tx, results, &stop_order.id)
+            }
+            None => self.process_market_order(tx, results, &stop_order.to_market_order(), None),
         }
     }
 
-    fn match_market_order_with_limit_order(
+    fn process_limit_maker_order(
         &mut self,
         tx: &mut Self::Transaction,
         results: &mut OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity>,
-        order: &MarketOrder<Self::OrderId, Self::Asset, Self::Quantity>,
-        opposite_order: &LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
-    ) -> Result<bool, Self::Err> {
-        let deal_time = self.current_timestamp_ms();
-        if order.quantity < opposite_order.quantity {
-            // market order: fully filled / limit order: partially filled
-            results.push(Ok(MatchingEngineOutput::Filled {
-                id: order.id,
-                side: order.side,
-                order_type: OrderType::Market,
-                price: opposite_order.price,
-                quantity: order.quantity,
-                timestamp_ms: deal_time,
-            }));
-            results.push(Ok(MatchingEngineOutput::PartiallyFilled {
-                id: opposite_order.id,
-                side: opposite_order.side,
-                order_type: OrderType::Limit,
-                price: opposite_order.price,
-                quantity: order.quantity,
-                timestamp_ms: deal_time,
+        limit_order: &LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+    ) -> Result<(), Self::Err> {
+        if let Some(crossing_order) = self.crossing_order(tx, limit_order)? {
+            let price = self.price_behind(limit_order.side, crossing_order.price);
+            match (limit_order.post_only, price) {
+                (Some(PostOnly::Reprice), Some(price)) => {
+                    results.push(Ok(MatchingEngineOutput::Accepted {
+                        id: limit_order.id,
+                        order_type: OrderType::LimitMaker,
+                        timestamp_ms: self.current_timestamp_ms(),
+                    }));
+                    results.push(Ok(MatchingEngineOutput::Repriced {
+                        id: limit_order.id,
+                        side: limit_order.side,
+                        price,
+                        timestamp_ms: self.current_timestamp_ms(),
+                    }));
+                    self.store_new_limit_order(tx, results, &limit_order.with_price(price))?;
+                }
+                _ => {
+                    results.push(Err(MatchingEngineFailure::WouldTakeLiquidity(
+                        limit_order.id,
+                    )));
+                    self.release_order(tx, results, &limit_order.id)?;
+                }
+            }
+        } else {
+            results.push(Ok(MatchingEngineOutput::Accepted {
+                id: limit_order.id,
+                order_type: OrderType::LimitMaker,
+                timestamp_ms: self.current_timestamp_ms(),
             }));
+            self.store_new_limit_order(tx, results, limit_order)?;
+        }
+        Ok(())
+    }
 
-            // modify unmatched part of the opposite limit order
-            let new_limit_order = opposite_order.sub_quantity(order.quantity);
-            match new_limit_order.side {
+    /// the best opposite order, hidden ones included, if the limit order would trade with it.
+    fn crossing_order(
+        &self,
+        tx: &mut Self::Transaction,
+        limit_order: &LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+    ) -> Result<Option<EngineLimitOrder<Self>>, Self::Err> {
+        let opposite_order = self.best_order(tx, limit_order.side.opposite(), |_| true)?;
+        Ok(
+            opposite_order.filter(|opposite_order| match limit_order.side {
+                OrderSide::Bid => limit_order.price >= opposite_order.price,
+                OrderSide::Ask => limit_order.price <= opposite_order.price,
+            }),
+        )
+    }
+
+    /// the price one tick behind `opposite_price` on the passive side, `None` below zero.
+    fn price_behind(&self, side: OrderSide, opposite_price: Self::Price) -> Option<Self::Price> {
+        match side {
+            OrderSide::Bid => checked_sub(opposite_price, self.tick_size()),
+            OrderSide::Ask => Some(opposite_price + self.tick_size()),
+        }
+    }
+
+    /// places a new pegged order at its pegged price. pegged orders never take liquidity.
+    fn process_pegged_order(
+        &mut self,
+        tx: &mut Self::Transaction,
+        results: &mut OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity>,
+        limit_order: &LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+    ) -> Result<(), Self::Err> {
+        let reference = self.peg_reference(tx)?;
+        match self.pegged_price(&reference, limit_order) {
+            Some(price) => {
+                results.push(Ok(MatchingEngineOutput::Accepted {
+                    id: limit_order.id,
+                    order_type: OrderType::Limit,
+                    timestamp_ms: self.current_timestamp_ms(),
+                }));
+                results.push(Ok(MatchingEngineOutput::Repriced {
+                    id: limit_order.id,
+                    side: limit_order.side,
+                    price,
+                    timestamp_ms: self.current_timestamp_ms(),
+                }));
+                self.process_unmatched_limit_order(tx, results, &limit_order.with_price(price))
+            }
+            None => {
+                results.push(Err(MatchingEngineFailure::NoReferencePrice(limit_order.id)));
+                self.release_order(tx, results, &limit_order.id)
+            }
+        }
+    }
+
+    /// moves the resting pegged orders to their pegged prices once the prices they follow
+    /// have changed from `reference`.
+    fn process_pegged_orders(
+        &mut self,
+        tx: &mut Self::Transaction,
+        results: &mut OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity>,
+        reference: &PegReference<Self::Price>,
+    ) -> Result<(), Self::Err> {
+        if self.peg_reference(tx)? == *reference {
+            return Ok(());
+        }
+        self.reprice_pegged_orders(tx, results)
+    }
+
+    /// moves the resting pegged orders to their pegged prices in the current book.
+    fn reprice_pegged_orders(
+        &mut self,
+        tx: &mut Self::Transaction,
+        results: &mut OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity>,
+    ) -> Result<(), Self::Err> {
+        let mut reference = self.peg_reference(tx)?;
+        let mut pegged_orders = self.bid_limit_order_repository().get_pegged_orders(tx)?;
+        pegged_orders.extend(self.ask_limit_order_repository().get_pegged_orders(tx)?);
+        for pegged_order in pegged_orders {
+            let price = match self.pegged_price(&reference, &pegged_order) {
+                Some(price) if price != pegged_order.price => price,
+                // the order stays while there is no reference price
+                _ => continue,
+            };
+            // the repriced order loses its time priority
+            let repriced_order = LimitOrder {
+                price,
+                timestamp_ms: self.current_timestamp_ms(),
+                ..pegged_order
+            };
+            match repriced_order.side {
                 OrderSide::Bid => self
                     .bid_limit_order_repository()
-                    .update(tx, &new_limit_order),
+                    .update(tx, &repriced_order),
                 OrderSide::Ask => self
                     .ask_limit_order_repository()
-                    .update(tx, &new_limit_order),
+                    .update(tx, &repriced_order),
             }?;
-            Ok(true)
-        } else if order.quantity > opposite_order.quantity {
-            // market order: partially filled / limit order: fully filled
-            results.push(Ok(MatchingEngineOutput::PartiallyFilled {
-                id: order.id,
-                side: order.side,
-                order_type: OrderType::Market,
-                price: opposite_order.price,
-                quantity: opposite_order.quantity,
-                timestamp_ms: deal_time,
-            }));
-            results.push(Ok(MatchingEngineOutput::Filled {
-                id: opposite_order.id,
-                side: opposite_order.side,
-                order_type: OrderType::Limit,
-                price: opposite_order.price,
-                quantity: opposite_order.quantity,
-                timestamp_ms: deal_time,
+            results.push(Ok(MatchingEngineOutput::Repriced {
+                id: repriced_order.id,
+                side: repriced_order.side,
+                price,
+                timestamp_ms: self.current_timestamp_ms(),
             }));
+            // a repriced order may bound the pegged orders of the other side
+            reference = self.peg_reference(tx)?;
+        }
+        Ok(())
+    }
 
-            // remove filled limit order from the queue
-            match opposite_order.side {
+    /// price of a pegged order at the given prices of the book, `None` without a reference
+    /// price. the price stays one tick behind the best opposite order, hidden ones included,
+    /// so that the order never crosses it.
+    fn pegged_price(
+        &self,
+        reference: &PegReference<Self::Price>,
+        limit_order: &LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+    ) -> Option<Self::Price> {
+        let peg = match limit_order.peg {
+            Some(peg) => peg,
+            None => return Some(limit_order.price),
+        };
+        let price = peg.price(
+            limit_order.side,
+            reference.best_bid,
+            reference.best_ask,
+            self.tick_size(),
+        )?;
+        match limit_order.side {
+            OrderSide::Bid => match reference.best_resting_ask {
+                Some(best_ask) if price >= best_ask => self.price_behind(OrderSide::Bid, best_ask),
+                _ => Some(price),
+            },
+            OrderSide::Ask => match reference.best_resting_bid {
+                Some(best_bid) if price <= best_bid => self.price_behind(OrderSide::Ask, best_bid),
+                _ => Some(price),
+            },
+        }
+    }
+
+    /// prices of the current book followed by the pegged orders.
+    /// hidden orders are left out as they are not part of the market data.
+    fn peg_reference(
+        &self,
+        tx: &mut Self::Transaction,
+    ) -> Result<PegReference<Self::Price>, Self::Err> {
+        let price = |order: Option<EngineLimitOrder<Self>>| order.map(|order| order.price);
+        let is_reference = |order: &EngineLimitOrder<Self>| order.peg.is_none() && !order.hidden;
+        let is_resting = |_: &EngineLimitOrder<Self>| true;
+        Ok(PegReference {
+            best_bid: price(self.best_order(tx, OrderSide::Bid, is_reference)?),
+            best_ask: price(self.best_order(tx, OrderSide::Ask, is_reference)?),
+            best_resting_bid: price(self.best_order(tx, OrderSide::Bid, is_resting)?),
+            best_resting_ask: price(self.best_order(tx, OrderSide::Ask, is_resting)?),
+        })
+    }
+
+    /// first unexpired order on the given side, in priority order, that is eligible.
+    fn best_order(
+        &self,
+        tx: &mut Self::Transaction,
+        side: OrderSide,
+        is_eligible: impl Fn(&EngineLimitOrder<Self>) -> bool,
+    ) -> Result<Option<EngineLimitOrder<Self>>, Self::Err> {
+        let now_ms = self.current_timestamp_ms();
+        let mut order = match side {
+            OrderSide::Bid => self.bid_limit_order_repository().next(tx)?,
+            OrderSide::Ask => self.ask_limit_order_repository().next(tx)?,
+        };
+        while let Some(limit_order) = order {
+            if is_eligible(&limit_order) && !self.has_expired(&limit_order, now_ms) {
+                return Ok(Some(limit_order));
+            }
+            order = match side {
                 OrderSide::Bid => self
                     .bid_limit_order_repository()
-                    .delete_by_order_id(tx, &opposite_order.id),
+                    .next_after(tx, &limit_order)?,
                 OrderSide::Ask => self
                     .ask_limit_order_repository()
-                    .delete_by_order_id(tx, &opposite_order.id),
-            }?;
-            Ok(false)
+                    .next_after(tx, &limit_order)?,
+            };
+        }
+        Ok(None)
+    }
+
+    fn process_unmatched_limit_order(
+        &mut self,
+        tx: &mut Self::Transaction,
+        results: &mut OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity>,
+        limit_order: &LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+    ) -> Result<(), Self::Err> {
+        let is_expired = limit_order.is_expired(self.current_timestamp_ms());
+        match limit_order.time_in_force {
+            TimeInForce::GTC | TimeInForce::DAY if !is_expired => {
+                self.store_new_limit_order(tx, results, limit_order)
+            }
+            _ => {
+                // expire the unmatched part instead of resting it in the book
+                results.push(Ok(MatchingEngineOutput::Expired {
+                    id: limit_order.id,
+                    side: limit_order.side,
+                    order_type: limit_order.order_type(),
+                    quantity: limit_order.quantity,
+                    timestamp_ms: self.current_timestamp_ms(),
+                }));
+                self.release_order(tx, results, &limit_order.id)?;
+                Ok(())
+            }
+        }
+    }
+
+    fn process_amend_order(
+        &mut self,
+        tx: &mut Self::Transaction,
+        results: &mut OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity>,
+        amend_order: &AmendOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+    ) -> Result<(), Self::Err> {
+        match amend_order.target_order_type {
+            OrderType::Limit | OrderType::LimitMaker | OrderType::MarketToLimit => {
+                let order = match amend_order.side {
+                    OrderSide::Bid => self
+                        .bid_limit_order_repository()
+                        .get_by_order_id(tx, &amend_order.target_id),
+                    OrderSide::Ask => self
+                        .ask_limit_order_repository()
+                        .get_by_order_id(tx, &amend_order.target_id),
+                }?;
+                if let Some(target_order) = order {
+                    // the amended quantity is the new total size of the order, fills included
+                    let filled_quantity = target_order.filled_quantity.unwrap_or_default();
+                    match amend_order.quantity {
+                        quantity if quantity < filled_quantity => {
+                            results.push(Err(MatchingEngineFailure::AmendBelowFilledQuantity {
+                                order_id: amend_order.id,
+                                target_order_id: amend_order.target_id,
+                            }));
+                        }
+                        quantity if quantity == filled_quantity => {
+                            // nothing is left to trade
+                            self.process_cancel_order(tx, results, &amend_order.cancel_order())?;
+                        }
+                        quantity => {
+                            let quantity = quantity - filled_quantity;
+                            if amend_order.price == target_order.price
+                                && quantity > Self::Quantity::default()
+                                && quantity <= target_order.remaining_quantity()
+                            {
+                                // a size-down to a non-zero quantity keeps the queue position
+                                // of the order
+                                let amended_order = target_order.reduced(quantity);
+                                match amended_order.side {
+                                    OrderSide::Bid => {
+                                        self.bid_limit_order_repository().update(tx, &amended_order)
+                                    }
+                                    OrderSide::Ask => {
+                                        self.ask_limit_order_repository().update(tx, &amended_order)
+                                    }
+                                }?;
+                                results.push(Ok(MatchingEngineOutput::Amended {
+                                    id: amend_order.id,
+                                    target_id: amend_order.target_id,
+                                    price: amend_order.price,
+                                    quantity: amend_order.quantity,
+                                    priority_retained: true,
+                                    timestamp_ms: self.current_timestamp_ms(),
+                                }));
+                            } else {
+                                let amended_order = target_order.amended(
+                                    amend_order.price,
+                                    quantity,
+                                    amend_order.timestamp_ms,
+                                );
+                                self.process_amended_limit_order(
+                                    tx,
+                                    results,
+                                    amend_order,
+                                    &amended_order,
+                                )?;
+                            }
+                        }
+                    }
+                } else {
+                    results.push(Err(MatchingEngineFailure::OrderNotFound {
+                        order_id: amend_order.id,
+                        target_order_id: amend_order.target_id,
+                    }));
+                }
+            }
+            OrderType::StopLoss
+            | OrderType::StopLossLimit
+            | OrderType::TakeProfit
+            | OrderType::TakeProfitLimit
+            | OrderType::TrailingStop => {
+                let order = self
+                    .stop_order_repository()
+                    .get_by_order_id(tx, &amend_order.target_id)?;
+                if order.is_some() && amend_order.quantity == Self::Quantity::default() {
+                    // nothing is left to trigger
+                    self.process_cancel_order(tx, results, &amend_order.cancel_order())?;
+                } else if let Some(mut target_order) = order {
+                    // a size-down keeps the priority of the order
+                    let priority_retained = amend_order
+                        .stop_price
+                        .into_iter()
+                        .all(|stop_price| stop_price == target_order.stop_price)
+                        && target_order
+                            .price
+                            .into_iter()
+                            .all(|price| price == amend_order.price)
+                        && amend_order.quantity <= target_order.quantity;
+                    if let Some(stop_price) = amend_order.stop_price {
+                        target_order.stop_price = stop_price;
+                    }
+                    if target_order.price.is_some() {
+                        target_order.price = Some(amend_order.price);
+                    }
+                    target_order.quantity = amend_order.quantity;
+                    if !priority_retained {
+                        target_order.timestamp_ms = amend_order.timestamp_ms;
+                    }
+                    self.stop_order_repository().update(tx, &target_order)?;
+                    results.push(Ok(MatchingEngineOutput::Amended {
+                        id: amend_order.id,
+                        target_id: amend_order.target_id,
+                        price: amend_order.price,
+                        quantity: amend_order.quantity,
+                        priority_retained,
+                        timestamp_ms: self.current_timestamp_ms(),
+                    }));
+                } else {
+                    results.push(Err(MatchingEngineFailure::OrderNotFound {
+                        order_id: amend_order.id,
+                        target_order_id: amend_order.target_id,
+                    }));
+                }
+            }
+            _ => { /* ignore */ }
+        }
+        Ok(())
+    }
+
+    /// replaces a resting limit order by its amended order through the matching, so that
+    /// an amend crossing the spread trades right away and only the remainder rests in the book.
+    /// the amended order loses its time priority.
+    /// the amend of a post-only order which would take liquidity is rejected.
+    fn process_amended_limit_order(
+        &mut self,
+        tx: &mut Self::Transaction,
+        results: &mut OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity>,
+        amend_order: &AmendOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+        amended_order: &LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+    ) -> Result<(), Self::Err> {
+        let crossing_order = if amended_order.post_only.is_some() {
+            self.crossing_order(tx, amended_order)?
         } else {
-            // exact match
-            results.push(Ok(MatchingEngineOutput::Filled {
-                id: order.id,
-                side: order.side,
-                order_type: OrderType::Market,
-                price: opposite_order.price,
-                quantity: order.quantity,
-                timestamp_ms: deal_time,
-            }));
-            results.push(Ok(MatchingEngineOutput::Filled {
-                id: opposite_order.id,
-                side: opposite_order.side,
-                order_type: OrderType::Limit,
-                price: opposite_order.price,
-                quantity: opposite_order.quantity,
-                timestamp_ms: deal_time,
+            None
+        };
+        let repriced_price = match (&crossing_order, amended_order.post_only) {
+            (Some(crossing_order), Some(PostOnly::Reprice)) => {
+                self.price_behind(amended_order.side, crossing_order.price)
+            }
+            _ => None,
+        };
+        if crossing_order.is_some() && repriced_price.is_none() {
+            results.push(Err(MatchingEngineFailure::WouldTakeLiquidity(
+                amend_order.id,
+            )));
+            return Ok(());
+        }
+        match amended_order.side {
+            OrderSide::Bid => self
+                .bid_limit_order_repository()
+                .delete_by_order_id(tx, &amended_order.id),
+            OrderSide::Ask => self
+                .ask_limit_order_repository()
+                .delete_by_order_id(tx, &amended_order.id),
+        }?;
+        results.push(Ok(MatchingEngineOutput::Amended {
+            id: amend_order.id,
+            target_id: amend_order.target_id,
+            price: amend_order.price,
+            quantity: amend_order.quantity,
+            priority_retained: false,
+            timestamp_ms: self.current_timestamp_ms(),
+        }));
+        if let Some(price) = repriced_price {
+            results.push(Ok(MatchingEngineOutput::Repriced {
+                id: amended_order.id,
+                side: amended_order.side,
+                price,
+                timestamp_ms: self.current_timestamp_ms(),
             }));
+            self.store_new_limit_order(tx, results, &amended_order.with_price(price))
+        } else if amended_order.post_only.is_some()
+            || amended_order.peg.is_some()
+            || self.session_state().collects_orders()
+        {
+            // pegged orders never take liquidity, and no order is matched in a call phase
+            self.store_new_limit_order(tx, results, amended_order)
+        } else {
+            self.process_limit_order(tx, results, amended_order)
+        }
+    }
 
-            // remove filled limit order from the queue
-            match opposite_order.side {
+    fn process_cancel_order(
+        &mut self,
+        tx: &mut Self::Transaction,
+        results: &mut OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity>,
+        cancel_order: &CancelOrder<Self::OrderId, Self::Asset>,
+    ) -> Result<(), Self::Err> {
+        match cancel_order.target_order_type {
+            OrderType::Limit | OrderType::LimitMaker | OrderType::MarketToLimit => {
+                match cancel_order.side {
+                    OrderSide::Bid => self
+                        .bid_limit_order_repository()
+                        .delete_by_order_id(tx, &cancel_order.target_id),
+                    OrderSide::Ask => self
+                        .ask_limit_order_repository()
+                        .delete_by_order_id(tx, &cancel_order.target_id),
+                }?;
+                results.push(Ok(MatchingEngineOutput::Cancelled {
+                    id: cancel_order.id,
+                    target_id: cancel_order.target_id,
+                    timestamp_ms: self.current_timestamp_ms(),
+                }));
+                self.cancel_child_orders(tx, results, &cancel_order.target_id)?;
+            }
+            OrderType::StopLoss
+            | OrderType::StopLossLimit
+            | OrderType::TakeProfit
+            | OrderType::TakeProfitLimit
+            | OrderType::TrailingStop => {
+                self.stop_order_repository()
+                    .delete_by_order_id(tx, &cancel_order.target_id)?;
+                results.push(Ok(MatchingEngineOutput::Cancelled {
+                    id: cancel_order.id,
+                    target_id: cancel_order.target_id,
+                    timestamp_ms: self.current_timestamp_ms(),
+                }));
+            }
+            _ => return Ok(()),
+        }
+        self.cancel_linked_order(tx, results, &cancel_order.target_id)
+    }
+
+    /// cancels the order linked to the given order, if any.
+    fn cancel_linked_order(
+        &mut self,
+        tx: &mut Self::Transaction,
+        results: &mut OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity>,
+        order_id: &Self::OrderId,
+    ) -> Result<(), Self::Err> {
+        if let Some(link) = self.unlink_order(tx, order_id)? {
+            let cancel_order = CancelOrder {
+                id: link.order_id,
+                asset_pair: self.asset_pair().clone(),
+                target_id: link.linked_order_id,
+                target_order_type: link.linked_order_type,
+                side: link.linked_side,
+            };
+            self.process_cancel_order(tx, results, &cancel_order)?;
+        }
+        Ok(())
+    }
+
+    /// cancels the child orders of the given order that have not been submitted yet.
+    /// submitted child orders keep protecting the filled quantity.
+    fn cancel_child_orders(
+        &mut self,
+        tx: &mut Self::Transaction,
+        results: &mut OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity>,
+        parent_id: &Self::OrderId,
+    ) -> Result<(), Self::Err> {
+        if let Some(child_orders) = self
+            .child_order_repository()
+            .get_by_parent_id(tx, parent_id)?
+        {
+            self.child_order_repository()
+                .delete_by_parent_id(tx, parent_id)?;
+            if child_orders.filled_quantity.is_none() {
+                for target_id in [
+                    child_orders.take_profit_order.id,
+                    child_orders.stop_loss_order.id,
+                ] {
+                    results.push(Ok(MatchingEngineOutput::Cancelled {
+                        id: *parent_id,
+                        target_id,
+                        timestamp_ms: self.current_timestamp_ms(),
+                    }));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// submits or resizes the child orders of the parent orders filled in `results[from..]`.
+    /// the child orders are queued until the matching which filled their parent is complete,
+    /// and the fills of the submitted child orders are processed in turn.
+    fn process_child_orders(
+        &mut self,
+        tx: &mut Self::Transaction,
+        results: &mut OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity>,
+        from: usize,
+    ) -> Result<(), Self::Err> {
+        let mut from = from;
+        let mut partially_filled_parents = vec![];
+        while from < results.len() {
+            let to = results.len();
+            let fills: Vec<(Self::OrderId, Self::Quantity, bool)> = results[from..to]
+                .iter()
+                .filter_map(|result| match result {
+                    Ok(MatchingEngineOutput::Filled { id, quantity, .. }) => {
+                        Some((*id, *quantity, true))
+                    }
+                    Ok(MatchingEngineOutput::PartiallyFilled { id, quantity, .. }) => {
+                        Some((*id, *quantity, false))
+                    }
+                    _ => None,
+                })
+                .collect();
+            for (parent_id, quantity, filled) in fills {
+                let mut child_orders = match self
+                    .child_order_repository()
+                    .get_by_parent_id(tx, &parent_id)?
+                {
+                    Some(child_orders) => child_orders,
+                    None => continue,
+                };
+                let filled_quantity = match child_orders.filled_quantity {
+                    Some(filled_quantity) => {
+                        self.resize_child_orders(tx, results, &child_orders, quantity)?;
+                        filled_quantity + quantity
+                    }
+                    None => {
+                        let oco_order = child_orders.to_oco_order(quantity);
+                        self.process_order_request(tx, results, OrderRequest::Oco(oco_order))?;
+                        quantity
+                    }
+                };
+                if filled {
+                    self.child_order_repository()
+                        .delete_by_parent_id(tx, &parent_id)?;
+                    partially_filled_parents.retain(|(id, _)| *id != parent_id);
+                } else {
+                    child_orders.filled_quantity = Some(filled_quantity);
+                    self.child_order_repository().update(tx, &child_orders)?;
+                    let entry_side = child_orders.take_profit_order.side.opposite();
+                    if !partially_filled_parents.contains(&(parent_id, entry_side)) {
+                        partially_filled_parents.push((parent_id, entry_side));
+                    }
+                }
+            }
+            from = to;
+        }
+        // the records of the parent orders released from the book are dropped once their
+        // child orders have been handled
+        for (parent_id, entry_side) in partially_filled_parents {
+            let parent_order = match entry_side {
                 OrderSide::Bid => self
                     .bid_limit_order_repository()
-                    .delete_by_order_id(tx, &opposite_order.id),
+                    .get_by_order_id(tx, &parent_id),
                 OrderSide::Ask => self
                     .ask_limit_order_repository()
-                    .delete_by_order_id(tx, &opposite_order.id),
+                    .get_by_order_id(tx, &parent_id),
             }?;
-            Ok(true)
+            if parent_order.is_none() {
+                self.child_order_repository()
+                    .delete_by_parent_id(tx, &parent_id)?;
+            }
         }
+        Ok(())
     }
 
-    fn match_limit_order_with_limit_order(
+    /// sizes the submitted child orders to the cumulative filled quantity of their parent.
+    /// the take-profit order loses its time priority on the increase. once the pair has been
+    /// unlinked by a fill or a cancel of either order, the quantity left unprotected is
+    /// covered by a new pair.
+    fn resize_child_orders(
         &mut self,
         tx: &mut Self::Transaction,
         results: &mut OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity>,
-        order: &LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
-        opposite_order: &LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
-    ) -> Result<bool, Self::Err> {
-        let deal_time = self.current_timestamp_ms();
-        if order.quantity < opposite_order.quantity {
-            // limit order: fully filled / limit order: partially filled
-            results.push(Ok(MatchingEngineOutput::Filled {
-                id: order.id,
-                side: order.side,
-                order_type: OrderType::Limit,
-                price: opposite_order.price,
-                quantity: order.quantity,
-                timestamp_ms: deal_time,
-            }));
-            results.push(Ok(MatchingEngineOutput::PartiallyFilled {
-                id: opposite_order.id,
-                side: opposite_order.side,
-                order_type: OrderType::Limit,
-                price: opposite_order.price,
-                quantity: order.quantity,
-                timestamp_ms: deal_time,
-            }));
-
-            // modify unmatched part of the opposite limit order
-            let new_limit_order = opposite_order.sub_quantity(order.quantity);
-            match new_limit_order.side {
+        child_orders: &ChildOrders<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+        quantity: Self::Quantity,
+    ) -> Result<(), Self::Err> {
+        let take_profit_id = child_orders.take_profit_order.id;
+        let stop_loss_id = child_orders.stop_loss_order.id;
+        let is_linked = self
+            .order_link_repository()
+            .get_by_order_id(tx, &take_profit_id)?
+            .is_some();
+        let take_profit_order = match child_orders.take_profit_order.side {
+            OrderSide::Bid => self
+                .bid_limit_order_repository()
+                .get_by_order_id(tx, &take_profit_id),
+            OrderSide::Ask => self
+                .ask_limit_order_repository()
+                .get_by_order_id(tx, &take_profit_id),
+        }?
+        .map(|take_profit_order| LimitOrder {
+            quantity: take_profit_order.quantity + quantity,
+            original_quantity: take_profit_order.original_quantity + quantity,
+            timestamp_ms: self.current_timestamp_ms(),
+            ..take_profit_order
+        });
+        if let Some(take_profit_order) = &take_profit_order {
+            match take_profit_order.side {
                 OrderSide::Bid => self
                     .bid_limit_order_repository()
-                    .update(tx, &new_limit_order),
+                    .update(tx, take_profit_order),
                 OrderSide::Ask => self
                     .ask_limit_order_repository()
-                    .update(tx, &new_limit_order),
+                    .update(tx, take_profit_order),
             }?;
-            Ok(true)
-        } else if order.quantity > opposite_order.quantity {
-            // market order: partially filled / limit order: fully filled
+        }
+        if is_linked {
+            if let Some(stop_loss_order) = self
+                .stop_order_repository()
+                .get_by_order_id(tx, &stop_loss_id)?
+            {
+                let stop_loss_order = StopOrder {
+                    quantity: stop_loss_order.quantity + quantity,
+                    ..stop_loss_order
+                };
+                self.stop_order_repository().update(tx, &stop_loss_order)?;
+            }
+            return Ok(());
+        }
+        match take_profit_order {
+            Some(take_profit_order) => {
+                // the stop-loss order cancelled by a partial fill of the take-profit order
+                // is placed again for the remainder of the take-profit order
+                let oco_order = OcoOrder {
+                    stop_order: StopOrder {
+                        quantity: take_profit_order.remaining_quantity(),
+                        ..child_orders.stop_loss_order.clone()
+                    },
+                    limit_order: take_profit_order,
+                };
+                let (limit_order_link, stop_order_link) = oco_order.links();
+                self.order_link_repository().create(tx, &limit_order_link)?;
+                self.order_link_repository().create(tx, &stop_order_link)?;
+                results.push(Ok(MatchingEngineOutput::Accepted {
+                    id: oco_order.stop_order.id,
+                    order_type: oco_order.stop_order.order_type,
+                    timestamp_ms: self.current_timestamp_ms(),
+                }));
+                self.stop_order_repository()
+                    .create(tx, &oco_order.stop_order)
+            }
+            None => {
+                let oco_order = child_orders.to_oco_order(quantity);
+                self.process_order_request(tx, results, OrderRequest::Oco(oco_order))
+            }
+        }
+    }
+
+    /// drops the links and the pending child orders of an order leaving the book without
+    /// a fill or a cancel. the linked order of a one-cancels-other order is cancelled,
+    /// and the child orders of a bracket order are cancelled before their submission.
+    /// the child orders of the fills in `results` are still handled by
+    /// `process_child_orders`, which drops the record afterwards.
+    fn release_order(
+        &mut self,
+        tx: &mut Self::Transaction,
+        results: &mut OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity>,
+        order_id: &Self::OrderId,
+    ) -> Result<(), Self::Err> {
+        self.cancel_linked_order(tx, results, order_id)?;
+        let is_filled = results.iter().any(|result| match result {
+            Ok(MatchingEngineOutput::Filled { id, .. })
+            | Ok(MatchingEngineOutput::PartiallyFilled { id, .. }) => id == order_id,
+            _ => false,
+        });
+        if !is_filled {
+            self.cancel_child_orders(tx, results, order_id)?;
+        }
+        Ok(())
+    }
+
+    /// removes the links between the given order and its linked order.
+    fn unlink_order(
+        &mut self,
+        tx: &mut Self::Transaction,
+        order_id: &Self::OrderId,
+    ) -> Result<Option<OrderLink<Self::OrderId>>, Self::Err> {
+        let link = self.order_link_repository().get_by_order_id(tx, order_id)?;
+        if let Some(link) = &link {
+            self.order_link_repository()
+                .delete_by_order_id(tx, &link.order_id)?;
+            self.order_link_repository()
+                .delete_by_order_id(tx, &link.linked_order_id)?;
+        }
+        Ok(link)
+    }
+
+    fn store_new_limit_order(
+        &mut self,
+        tx: &mut Self::Transaction,
+        _results: &mut OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity>,
+        limit_order: &LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+    ) -> Result<(), Self::Err> {
+        let mut limit_order = limit_order.displayed();
+        limit_order.participant_role = self.participant_role(limit_order.owner);
+        if let MatchingPolicy::TopOrderProRata { .. } = self.matching_policy() {
+            // the order establishing a new best price on its side becomes the top order
+            let best_order = match limit_order.side {
+                OrderSide::Bid => self.bid_limit_order_repository().next(tx),
+                OrderSide::Ask => self.ask_limit_order_repository().next(tx),
+            }?;
+            limit_order.top_order = match best_order {
+                Some(best_order) => match limit_order.side {
+                    OrderSide::Bid => limit_order.price > best_order.price,
+                    OrderSide::Ask => limit_order.price < best_order.price,
+                },
+                None => true,
+            };
+        }
+        match limit_order.side {
+            OrderSide::Bid => self.bid_limit_order_repository().create(tx, &limit_order),
+            OrderSide::Ask => self.ask_limit_order_repository().create(tx, &limit_order),
+        }
+    }
+
+    /// removes the opposite limit order whose displayed quantity has been fully filled,
+    /// or replenishes the next peak of an iceberg order.
+    fn process_filled_limit_order(
+        &mut self,
+        tx: &mut Self::Transaction,
+        results: &mut OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity>,
+        opposite_order: &LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+        price: Self::Price,
+        deal_time: u64,
+    ) -> Result<(), Self::Err> {
+        if let Some(next_peak) = opposite_order
+            .add_filled_quantity(opposite_order.quantity)
+            .replenished(deal_time)
+        {
             results.push(Ok(MatchingEngineOutput::PartiallyFilled {
-                id: order.id,
-                side: order.side,
-                order_type: OrderType::Limit,
-                price: opposite_order.price,
+                id: opposite_order.id,
+                side: opposite_order.side,
+                order_type: opposite_order.order_type(),
+                price,
                 quantity: opposite_order.quantity,
+                quote_quantity: opposite_order.quantity.notional(price),
                 timestamp_ms: deal_time,
             }));
+            match next_peak.side {
+                OrderSide::Bid => self.bid_limit_order_repository().update(tx, &next_peak),
+                OrderSide::Ask => self.ask_limit_order_repository().update(tx, &next_peak),
+            }
+        } else {
             results.push(Ok(MatchingEngineOutput::Filled {
                 id: opposite_order.id,
                 side: opposite_order.side,
-                order_type: OrderType::Limit,
-                price: opposite_order.price,
+                order_type: opposite_order.order_type(),
+                price,
                 quantity: opposite_order.quantity,
+                quote_quantity: opposite_order.quantity.notional(price),
                 timestamp_ms: deal_time,
             }));
 
@@ -409,37 +1802,535 @@ pub trait MatchingEngine {
                 OrderSide::Ask => self
                     .ask_limit_order_repository()
                     .delete_by_order_id(tx, &opposite_order.id),
-            }?;
-            Ok(false)
-        } else {
-            // exact match
-            results.push(Ok(MatchingEngineOutput::Filled {
-                id: order.id,
-                side: order.side,
-                order_type: OrderType::Limit,
-                price: opposite_order.price,
-                quantity: order.quantity,
-                timestamp_ms: deal_time,
-            }));
-            results.push(Ok(MatchingEngineOutput::Filled {
-                id: opposite_order.id,
-                side: opposite_order.side,
-                order_type: OrderType::Limit,
-                price: opposite_order.price,
-                quantity: opposite_order.quantity,
+            }
+        }
+    }
+
+    /// fills a resting limit order by the given quantity at the trade price.
+    fn fill_limit_order(
+        &mut self,
+        tx: &mut Self::Transaction,
+        results: &mut OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity>,
+        limit_order: &LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+        quantity: Self::Quantity,
+        price: Self::Price,
+        deal_time: u64,
+    ) -> Result<(), Self::Err> {
+        if quantity < limit_order.quantity {
+            results.push(Ok(MatchingEngineOutput::PartiallyFilled {
+                id: limit_order.id,
+                side: limit_order.side,
+                order_type: limit_order.order_type(),
+                price,
+                quantity,
+                quote_quantity: quantity.notional(price),
                 timestamp_ms: deal_time,
             }));
+            let new_limit_order = limit_order.fill(quantity);
+            match new_limit_order.side {
+                OrderSide::Bid => self
+                    .bid_limit_order_repository()
+                    .update(tx, &new_limit_order),
+                OrderSide::Ask => self
+                    .ask_limit_order_repository()
+                    .update(tx, &new_limit_order),
+            }
+        } else {
+            self.process_filled_limit_order(tx, results, limit_order, price, deal_time)
+        }
+    }
 
-            // remove filled limit order from the queue
-            match opposite_order.side {
+    /// matches the best bid and ask orders of an uncross at the clearing price.
+    fn match_auction_orders(
+        &mut self,
+        tx: &mut Self::Transaction,
+        results: &mut OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity>,
+        bid_order: &LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+        ask_order: &LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+        price: Self::Price,
+    ) -> Result<(), Self::Err> {
+        let deal_time = self.current_timestamp_ms();
+        let quantity = bid_order.quantity.min(ask_order.quantity);
+        self.fill_limit_order(tx, results, bid_order, quantity, price, deal_time)?;
+        self.fill_limit_order(tx, results, ask_order, quantity, price, deal_time)?;
+
+        // a fill of a one-cancels-other order cancels the linked order
+        self.cancel_linked_order(tx, results, &bid_order.id)?;
+        self.cancel_linked_order(tx, results, &ask_order.id)?;
+        Ok(())
+    }
+
+    /// applies the self-trade prevention to the best bid and ask orders of an uncross placed by
+    /// the same owner. the newest of the two orders takes the place of the incoming order.
+    fn prevent_auction_self_trade(
+        &mut self,
+        tx: &mut Self::Transaction,
+        results: &mut OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity>,
+        bid_order: &LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+        ask_order: &LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+    ) -> Result<(), Self::Err> {
+        let (newest_order, oldest_order) = if bid_order.timestamp_ms > ask_order.timestamp_ms {
+            (bid_order, ask_order)
+        } else {
+            (ask_order, bid_order)
+        };
+        let quantity = newest_order.remaining_quantity();
+        let cancelled_quantity = match self.prevent_self_trade(
+            tx,
+            results,
+            newest_order.id,
+            newest_order.side,
+            quantity,
+            oldest_order,
+        )? {
+            Some(cancelled_quantity) => cancelled_quantity,
+            None => return Ok(()),
+        };
+        if cancelled_quantity < quantity {
+            // the decremented order keeps its original quantity like the resting orders
+            let decremented_order = LimitOrder {
+                original_quantity: newest_order.original_quantity,
+                ..newest_order.reduced(quantity - cancelled_quantity)
+            };
+            match decremented_order.side {
                 OrderSide::Bid => self
                     .bid_limit_order_repository()
-                    .delete_by_order_id(tx, &opposite_order.id),
+                    .update(tx, &decremented_order),
                 OrderSide::Ask => self
                     .ask_limit_order_repository()
-                    .delete_by_order_id(tx, &opposite_order.id),
-            }?;
-            Ok(true)
+                    .update(tx, &decremented_order),
+            }
+        } else {
+            match newest_order.side {
+                OrderSide::Bid => self
+                    .bid_limit_order_repository()
+                    .delete_by_order_id(tx, &newest_order.id),
+                OrderSide::Ask => self
+                    .ask_limit_order_repository()
+                    .delete_by_order_id(tx, &newest_order.id),
+            }
+        }
+    }
+
+    /// allocates the quantity of an incoming order across the orders at the price of
+    /// `opposite_order` in one step by the matching policy. the hidden orders at the
+    /// price form a level of their own behind the displayed orders.
+    /// the resting orders of the owner of the incoming order are left out of the allocation.
+    /// returns whether the incoming order is completely filled and the traded quantity.
+    fn match_order_with_price_level(
+        &mut self,
+        tx: &mut Self::Transaction,
+        results: &mut OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity>,
+        order: &LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+        order_type: OrderType,
+        opposite_order: &LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+        policy: MatchingPolicy<Self::Quantity>,
+    ) -> Result<(bool, Self::Quantity), Self::Err> {
+        let now_ms = self.current_timestamp_ms();
+        let mut level_orders = vec![opposite_order.clone()];
+        let mut next_order = self.next_after(tx, opposite_order)?;
+        while let Some(level_order) = next_order {
+            if level_order.price != opposite_order.price
+                || level_order.hidden != opposite_order.hidden
+            {
+                break;
+            }
+            next_order = self.next_after(tx, &level_order)?;
+            // the orders of the same owner have been removed by the self-trade prevention
+            if !level_order.is_expired(now_ms) {
+                level_orders.push(level_order);
+            }
         }
+        let allocations = allocation::allocations(
+            policy,
+            order.quantity,
+            &level_orders
+                .iter()
+                .map(|level_order| allocation::LevelOrder {
+                    quantity: level_order.quantity,
+                    top_order: level_order.top_order,
+                    lead_market_maker: level_order.participant_role
+                        == ParticipantRole::LeadMarketMaker,
+                })
+                .collect::<Vec<_>>(),
+        );
+
+        let deal_time = self.current_timestamp_ms();
+        let price = opposite_order.price;
+        let mut remaining = Some(order.quantity);
+        let mut traded_quantity = None;
+        let mut filled_order_ids = vec![];
+        for (level_order, allocation) in level_orders.iter().zip(allocations) {
+            let (allocation, rest) = match (allocation, remaining) {
+                (Some(allocation), Some(rest)) => (allocation.min(rest), rest),
+                _ => continue,
+            };
+            remaining = if allocation < rest {
+                Some(rest - allocation)
+            } else {
+                None
+            };
+            traded_quantity =
+                Some(traded_quantity.map_or(allocation, |traded| traded + allocation));
+            if remaining.is_some() {
+                results.push(Ok(MatchingEngineOutput::PartiallyFilled {
+                    id: order.id,
+                    side: order.side,
+                    order_type,
+                    price,
+                    quantity: allocation,
+                    quote_quantity: allocation.notional(price),
+                    timestamp_ms: deal_time,
+                }));
+            } else {
+                results.push(Ok(MatchingEngineOutput::Filled {
+                    id: order.id,
+                    side: order.side,
+                    order_type,
+                    price,
+                    quantity: allocation,
+                    quote_quantity: allocation.notional(price),
+                    timestamp_ms: deal_time,
+                }));
+            }
+            self.fill_limit_order(tx, results, level_order, allocation, price, deal_time)?;
+            filled_order_ids.push(level_order.id);
+        }
+
+        // a fill of a one-cancels-other order cancels the linked order
+        self.cancel_linked_order(tx, results, &order.id)?;
+        for filled_order_id in filled_order_ids.iter() {
+            self.cancel_linked_order(tx, results, filled_order_id)?;
+        }
+        Ok((
+            remaining.is_none(),
+            traded_quantity.unwrap_or(order.quantity),
+        ))
+    }
+
+    /// returns the resting order of the same owner which the incoming order meets at the best
+    /// opposite order `opposite_order`: the best opposite order itself under price-time priority,
+    /// or any order of its price level under pro-rata matching as the level is allocated at once.
+    fn self_trade_order(
+        &self,
+        tx: &mut Self::Transaction,
+        owner: Option<u64>,
+        opposite_order: &LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+    ) -> Result<Option<EngineLimitOrder<Self>>, Self::Err> {
+        if is_self_trade(owner, opposite_order) {
+            return Ok(Some(opposite_order.clone()));
+        }
+        if owner.is_none() || matches!(self.matching_policy(), MatchingPolicy::Fifo) {
+            return Ok(None);
+        }
+        let now_ms = self.current_timestamp_ms();
+        let mut next_order = self.next_after(tx, opposite_order)?;
+        while let Some(level_order) = next_order {
+            if level_order.price != opposite_order.price
+                || level_order.hidden != opposite_order.hidden
+            {
+                break;
+            }
+            if !level_order.is_expired(now_ms) && is_self_trade(owner, &level_order) {
+                return Ok(Some(level_order));
+            }
+            next_order = self.next_after(tx, &level_order)?;
+        }
+        Ok(None)
+    }
+
+    /// applies the self-trade prevention to an incoming order meeting `opposite_order` of the same
+    /// owner. `quantity` is the base quantity of the incoming order at the opposite price.
+    /// returns the quantity cancelled from the incoming order, `None` if it is left untouched.
+    fn prevent_self_trade(
+        &mut self,
+        tx: &mut Self::Transaction,
+        results: &mut OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity>,
+        id: Self::OrderId,
+        side: OrderSide,
+        quantity: Self::Quantity,
+        opposite_order: &LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+    ) -> Result<Option<Self::Quantity>, Self::Err> {
+        let mode = self.self_trade_prevention();
+        let (cancelled_quantity, opposite_cancelled_quantity) = match mode {
+            SelfTradePrevention::CancelNewest => (Some(quantity), None),
+            SelfTradePrevention::CancelOldest => (None, Some(opposite_order.remaining_quantity())),
+            SelfTradePrevention::CancelBoth => {
+                (Some(quantity), Some(opposite_order.remaining_quantity()))
+            }
+            SelfTradePrevention::DecrementAndCancel => {
+                let decrement = quantity.min(opposite_order.quantity);
+                (Some(decrement), Some(decrement))
+            }
+        };
+        let now_ms = self.current_timestamp_ms();
+        results.push(Ok(MatchingEngineOutput::SelfTradePrevented {
+            id,
+            side,
+            opposite_id: opposite_order.id,
+            mode,
+            quantity: cancelled_quantity,
+            opposite_quantity: opposite_cancelled_quantity,
+            timestamp_ms: now_ms,
+        }));
+        if let Some(opposite_cancelled_quantity) = opposite_cancelled_quantity {
+            // a decremented iceberg order replenishes its next peak
+            let next_order = if opposite_cancelled_quantity < opposite_order.quantity {
+                Some(opposite_order.sub_quantity(opposite_cancelled_quantity))
+            } else if mode == SelfTradePrevention::DecrementAndCancel {
+                opposite_order.replenished(now_ms)
+            } else {
+                None
+            };
+            match next_order {
+                Some(next_order) => match next_order.side {
+                    OrderSide::Bid => self.bid_limit_order_repository().update(tx, &next_order),
+                    OrderSide::Ask => self.ask_limit_order_repository().update(tx, &next_order),
+                }?,
+                None => {
+                    match opposite_order.side {
+                        OrderSide::Bid => self
+                            .bid_limit_order_repository()
+                            .delete_by_order_id(tx, &opposite_order.id),
+                        OrderSide::Ask => self
+                            .ask_limit_order_repository()
+                            .delete_by_order_id(tx, &opposite_order.id),
+                    }?;
+                    self.release_order(tx, results, &opposite_order.id)?;
+                }
+            }
+        }
+        if matches!(cancelled_quantity, Some(cancelled_quantity) if cancelled_quantity >= quantity)
+        {
+            self.release_order(tx, results, &id)?;
+        }
+        Ok(cancelled_quantity)
+    }
+
+    /// the resting order following the given order on its side.
+    fn next_after(
+        &self,
+        tx: &mut Self::Transaction,
+        limit_order: &LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+    ) -> Result<Option<EngineLimitOrder<Self>>, Self::Err> {
+        match limit_order.side {
+            OrderSide::Bid => self
+                .bid_limit_order_repository()
+                .next_after(tx, limit_order),
+            OrderSide::Ask => self
+                .ask_limit_order_repository()
+                .next_after(tx, limit_order),
+        }
+    }
+
+    fn match_market_order_with_limit_order(
+        &mut self,
+        tx: &mut Self::Transaction,
+        results: &mut OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity>,
+        order: &MarketOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+        quantity: Self::Quantity,
+        opposite_order: &LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+    ) -> Result<bool, Self::Err> {
+        let deal_time = self.current_timestamp_ms();
+        let matching_complete = match quantity.cmp(&opposite_order.quantity) {
+            Ordering::Less => {
+                // market order: fully filled / limit order: partially filled
+                results.push(Ok(MatchingEngineOutput::Filled {
+                    id: order.id,
+                    side: order.side,
+                    order_type: OrderType::Market,
+                    price: opposite_order.price,
+                    quantity,
+                    quote_quantity: quantity.notional(opposite_order.price),
+                    timestamp_ms: deal_time,
+                }));
+                results.push(Ok(MatchingEngineOutput::PartiallyFilled {
+                    id: opposite_order.id,
+                    side: opposite_order.side,
+                    order_type: opposite_order.order_type(),
+                    price: opposite_order.price,
+                    quantity,
+                    quote_quantity: quantity.notional(opposite_order.price),
+                    timestamp_ms: deal_time,
+                }));
+
+                // modify unmatched part of the opposite limit order
+                let new_limit_order = opposite_order.fill(quantity);
+                match new_limit_order.side {
+                    OrderSide::Bid => self
+                        .bid_limit_order_repository()
+                        .update(tx, &new_limit_order),
+                    OrderSide::Ask => self
+                        .ask_limit_order_repository()
+                        .update(tx, &new_limit_order),
+                }?;
+                true
+            }
+            Ordering::Greater => {
+                // market order: partially filled / limit order: fully filled
+                results.push(Ok(MatchingEngineOutput::PartiallyFilled {
+                    id: order.id,
+                    side: order.side,
+                    order_type: OrderType::Market,
+                    price: opposite_order.price,
+                    quantity: opposite_order.quantity,
+                    quote_quantity: opposite_order.quantity.notional(opposite_order.price),
+                    timestamp_ms: deal_time,
+                }));
+                self.process_filled_limit_order(
+                    tx,
+                    results,
+                    opposite_order,
+                    opposite_order.price,
+                    deal_time,
+                )?;
+                false
+            }
+            Ordering::Equal => {
+                // exact match
+                results.push(Ok(MatchingEngineOutput::Filled {
+                    id: order.id,
+                    side: order.side,
+                    order_type: OrderType::Market,
+                    price: opposite_order.price,
+                    quantity,
+                    quote_quantity: quantity.notional(opposite_order.price),
+                    timestamp_ms: deal_time,
+                }));
+                self.process_filled_limit_order(
+                    tx,
+                    results,
+                    opposite_order,
+                    opposite_order.price,
+                    deal_time,
+                )?;
+                true
+            }
+        };
+
+        // a fill of a one-cancels-other order cancels the linked order
+        self.cancel_linked_order(tx, results, &opposite_order.id)?;
+        Ok(matching_complete)
     }
+
+    fn match_limit_order_with_limit_order(
+        &mut self,
+        tx: &mut Self::Transaction,
+        results: &mut OrderProcessingResult<Self::OrderId, Self::Price, Self::Quantity>,
+        order: &LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+        opposite_order: &LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+    ) -> Result<bool, Self::Err> {
+        let deal_time = self.current_timestamp_ms();
+        let matching_complete = match order.quantity.cmp(&opposite_order.quantity) {
+            Ordering::Less => {
+                // limit order: fully filled / limit order: partially filled
+                results.push(Ok(MatchingEngineOutput::Filled {
+                    id: order.id,
+                    side: order.side,
+                    order_type: order.order_type(),
+                    price: opposite_order.price,
+                    quantity: order.quantity,
+                    quote_quantity: order.quantity.notional(opposite_order.price),
+                    timestamp_ms: deal_time,
+                }));
+                results.push(Ok(MatchingEngineOutput::PartiallyFilled {
+                    id: opposite_order.id,
+                    side: opposite_order.side,
+                    order_type: opposite_order.order_type(),
+                    price: opposite_order.price,
+                    quantity: order.quantity,
+                    quote_quantity: order.quantity.notional(opposite_order.price),
+                    timestamp_ms: deal_time,
+                }));
+
+                // modify unmatched part of the opposite limit order
+                let new_limit_order = opposite_order.fill(order.quantity);
+                match new_limit_order.side {
+                    OrderSide::Bid => self
+                        .bid_limit_order_repository()
+                        .update(tx, &new_limit_order),
+                    OrderSide::Ask => self
+                        .ask_limit_order_repository()
+                        .update(tx, &new_limit_order),
+                }?;
+                true
+            }
+            Ordering::Greater => {
+                // market order: partially filled / limit order: fully filled
+                results.push(Ok(MatchingEngineOutput::PartiallyFilled {
+                    id: order.id,
+                    side: order.side,
+                    order_type: order.order_type(),
+                    price: opposite_order.price,
+                    quantity: opposite_order.quantity,
+                    quote_quantity: opposite_order.quantity.notional(opposite_order.price),
+                    timestamp_ms: deal_time,
+                }));
+                self.process_filled_limit_order(
+                    tx,
+                    results,
+                    opposite_order,
+                    opposite_order.price,
+                    deal_time,
+                )?;
+                false
+            }
+            Ordering::Equal => {
+                // exact match
+                results.push(Ok(MatchingEngineOutput::Filled {
+                    id: order.id,
+                    side: order.side,
+                    order_type: order.order_type(),
+                    price: opposite_order.price,
+                    quantity: order.quantity,
+                    quote_quantity: order.quantity.notional(opposite_order.price),
+                    timestamp_ms: deal_time,
+                }));
+                self.process_filled_limit_order(
+                    tx,
+                    results,
+                    opposite_order,
+                    opposite_order.price,
+                    deal_time,
+                )?;
+                true
+            }
+        };
+
+        // a fill of a one-cancels-other order cancels the linked order
+        self.cancel_linked_order(tx, results, &order.id)?;
+        self.cancel_linked_order(tx, results, &opposite_order.id)?;
+        Ok(matching_complete)
+    }
+}
+
+/// `true` if the resting order belongs to the given owner.
+fn is_self_trade<ID: OrderId, A: Asset, P: Price, Q: Quantity>(
+    owner: Option<u64>,
+    opposite_order: &LimitOrder<ID, A, P, Q>,
+) -> bool {
+    owner.is_some() && owner == opposite_order.owner
+}
+
+fn last_trade_price<ID: OrderId, P: Price, Q: Quantity>(
+    results: &[OrderProcessingOutput<ID, P, Q>],
+) -> Option<P> {
+    results.iter().rev().find_map(|result| match result {
+        Ok(MatchingEngineOutput::Filled { price, .. })
+        | Ok(MatchingEngineOutput::PartiallyFilled { price, .. }) => Some(*price),
+        _ => None,
+    })
+}
+
+fn trade_price_range<ID: OrderId, P: Price, Q: Quantity>(
+    results: &[OrderProcessingOutput<ID, P, Q>],
+) -> Option<(P, P)> {
+    results.iter().fold(None, |range, result| match result {
+        Ok(MatchingEngineOutput::Filled { price, .. })
+        | Ok(MatchingEngineOutput::PartiallyFilled { price, .. }) => match range {
+            Some((low, high)) => Some((*price.min(&low), *price.max(&high))),
+            None => Some((*price, *price)),
+        },
+        _ => range,
+    })
 }
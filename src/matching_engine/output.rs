@@ -1,4 +1,7 @@
-use crate::{OrderId, OrderSide, OrderType, Price, Quantity};
+use crate::{
+    AuctionClearing, MarketOrderQuantity, OrderId, OrderSide, OrderType, Price, Quantity,
+    SelfTradePrevention,
+};
 
 #[derive(Debug)]
 pub enum MatchingEngineOutput<ID: OrderId, P: Price, Q: Quantity> {
@@ -8,12 +11,29 @@ pub enum MatchingEngineOutput<ID: OrderId, P: Price, Q: Quantity> {
         timestamp_ms: u64,
     },
 
+    Triggered {
+        id: ID,
+        side: OrderSide,
+        order_type: OrderType,
+        stop_price: P,
+        timestamp_ms: u64,
+    },
+
+    TrailingStopMoved {
+        id: ID,
+        side: OrderSide,
+        stop_price: P,
+        timestamp_ms: u64,
+    },
+
     Filled {
         id: ID,
         side: OrderSide,
         order_type: OrderType,
         price: P,
         quantity: Q,
+        /// notional of `quantity` in the quote asset
+        quote_quantity: Q,
         timestamp_ms: u64,
     },
 
@@ -23,14 +43,27 @@ pub enum MatchingEngineOutput<ID: OrderId, P: Price, Q: Quantity> {
         order_type: OrderType,
         price: P,
         quantity: Q,
+        /// notional of `quantity` in the quote asset
+        quote_quantity: Q,
         timestamp_ms: u64,
     },
 
+    Repriced {
+        id: ID,
+        side: OrderSide,
+        price: P,
+        timestamp_ms: u64,
+    },
+
+    /// an amend keeps the time priority of the order if only its quantity goes down.
+    /// a price change or a quantity increase moves the order to the end of the queue.
     Amended {
         id: ID,
         target_id: ID,
         price: P,
         quantity: Q,
+        /// `true` if the amended order kept its time priority
+        priority_retained: bool,
         timestamp_ms: u64,
     },
 
@@ -39,4 +72,40 @@ pub enum MatchingEngineOutput<ID: OrderId, P: Price, Q: Quantity> {
         target_id: ID,
         timestamp_ms: u64,
     },
+
+    Expired {
+        id: ID,
+        side: OrderSide,
+        order_type: OrderType,
+        quantity: Q,
+        timestamp_ms: u64,
+    },
+
+    /// unfilled remainder of a market order that is not traded any further.
+    RemainderCancelled {
+        id: ID,
+        side: OrderSide,
+        quantity: MarketOrderQuantity<Q>,
+        timestamp_ms: u64,
+    },
+
+    /// trade between the incoming order `id` and the resting order `opposite_id` of the same owner,
+    /// prevented by `mode`. `quantity` and `opposite_quantity` are the quantities cancelled from
+    /// each order, `None` if the order is left untouched.
+    SelfTradePrevented {
+        id: ID,
+        side: OrderSide,
+        opposite_id: ID,
+        mode: SelfTradePrevention,
+        quantity: Option<Q>,
+        opposite_quantity: Option<Q>,
+        timestamp_ms: u64,
+    },
+
+    /// indicative clearing of the call auction, published on every change of the order book
+    /// during the call phase. `clearing` is `None` if the book is not crossed.
+    IndicativeAuction {
+        clearing: Option<AuctionClearing<P, Q>>,
+        timestamp_ms: u64,
+    },
 }
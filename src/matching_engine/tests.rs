@@ -0,0 +1,2541 @@
+use super::*;
+use crate::model::{AssetPair, PegType, PriceProtection, TimeInForce, TrailingOffset};
+use std::cell::{Cell, RefCell};
+
+type TestLimitOrder = LimitOrder<u64, &'static str, u64, u64>;
+type TestStopOrder = StopOrder<u64, &'static str, u64, u64>;
+type TestChildOrders = ChildOrders<u64, &'static str, u64, u64>;
+
+/// one side of the order book kept in memory.
+struct TestLimitOrderRepository {
+    side: OrderSide,
+    orders: RefCell<Vec<TestLimitOrder>>,
+    /// number of scans for the pegged orders
+    pegged_order_scans: Cell<usize>,
+}
+
+impl TestLimitOrderRepository {
+    fn new(side: OrderSide) -> Self {
+        Self {
+            side,
+            orders: RefCell::new(vec![]),
+            pegged_order_scans: Cell::new(0),
+        }
+    }
+
+    fn sorted(&self) -> Vec<TestLimitOrder> {
+        let mut orders = self.orders.borrow().clone();
+        orders.sort_by(|a, b| {
+            let price = match self.side {
+                OrderSide::Bid => b.price.cmp(&a.price),
+                OrderSide::Ask => a.price.cmp(&b.price),
+            };
+            price
+                .then(a.hidden.cmp(&b.hidden))
+                .then(a.timestamp_ms.cmp(&b.timestamp_ms))
+                .then(a.id.cmp(&b.id))
+        });
+        orders
+    }
+}
+
+impl LimitOrderRepositoryLike for TestLimitOrderRepository {
+    type Err = ();
+    type Asset = &'static str;
+    type OrderId = u64;
+    type Price = u64;
+    type Quantity = u64;
+    type Transaction = ();
+
+    fn create(&self, _: &mut (), order: &TestLimitOrder) -> Result<(), ()> {
+        self.orders.borrow_mut().push(order.clone());
+        Ok(())
+    }
+
+    fn update(&self, _: &mut (), order: &TestLimitOrder) -> Result<(), ()> {
+        for stored in self.orders.borrow_mut().iter_mut() {
+            if stored.id == order.id {
+                *stored = order.clone();
+            }
+        }
+        Ok(())
+    }
+
+    fn delete_by_order_id(&self, _: &mut (), order_id: &u64) -> Result<(), ()> {
+        self.orders
+            .borrow_mut()
+            .retain(|order| order.id != *order_id);
+        Ok(())
+    }
+
+    fn get_by_order_id(&self, _: &mut (), order_id: &u64) -> Result<Option<TestLimitOrder>, ()> {
+        Ok(self
+            .orders
+            .borrow()
+            .iter()
+            .find(|order| order.id == *order_id)
+            .cloned())
+    }
+
+    fn next(&self, _: &mut ()) -> Result<Option<TestLimitOrder>, ()> {
+        Ok(self.sorted().first().cloned())
+    }
+
+    fn next_after(&self, _: &mut (), order: &TestLimitOrder) -> Result<Option<TestLimitOrder>, ()> {
+        Ok(self
+            .sorted()
+            .into_iter()
+            .skip_while(|stored| stored.id != order.id)
+            .nth(1))
+    }
+
+    fn get_pegged_orders(&self, _: &mut ()) -> Result<Vec<TestLimitOrder>, ()> {
+        self.pegged_order_scans
+            .set(self.pegged_order_scans.get() + 1);
+        Ok(self
+            .orders
+            .borrow()
+            .iter()
+            .filter(|order| order.peg.is_some())
+            .cloned()
+            .collect())
+    }
+}
+
+#[derive(Default)]
+struct TestStopOrderRepository(RefCell<Vec<TestStopOrder>>);
+
+impl StopOrderRepositoryLike for TestStopOrderRepository {
+    type Err = ();
+    type Asset = &'static str;
+    type OrderId = u64;
+    type Price = u64;
+    type Quantity = u64;
+    type Transaction = ();
+
+    fn create(&self, _: &mut (), order: &TestStopOrder) -> Result<(), ()> {
+        self.0.borrow_mut().push(order.clone());
+        Ok(())
+    }
+
+    fn update(&self, _: &mut (), order: &TestStopOrder) -> Result<(), ()> {
+        for stored in self.0.borrow_mut().iter_mut() {
+            if stored.id == order.id {
+                *stored = order.clone();
+            }
+        }
+        Ok(())
+    }
+
+    fn delete_by_order_id(&self, _: &mut (), order_id: &u64) -> Result<(), ()> {
+        self.0.borrow_mut().retain(|order| order.id != *order_id);
+        Ok(())
+    }
+
+    fn get_by_order_id(&self, _: &mut (), order_id: &u64) -> Result<Option<TestStopOrder>, ()> {
+        Ok(self
+            .0
+            .borrow()
+            .iter()
+            .find(|order| order.id == *order_id)
+            .cloned())
+    }
+
+    fn next_triggered(&self, _: &mut (), last_price: &u64) -> Result<Option<TestStopOrder>, ()> {
+        Ok(self
+            .0
+            .borrow()
+            .iter()
+            .find(|order| order.is_triggered_by(*last_price))
+            .cloned())
+    }
+
+    fn get_trailing_stop_orders(&self, _: &mut ()) -> Result<Vec<TestStopOrder>, ()> {
+        Ok(self
+            .0
+            .borrow()
+            .iter()
+            .filter(|order| order.trailing_stop.is_some())
+            .cloned()
+            .collect())
+    }
+}
+
+#[derive(Default)]
+struct TestOrderLinkRepository(RefCell<Vec<OrderLink<u64>>>);
+
+impl OrderLinkRepositoryLike for TestOrderLinkRepository {
+    type Err = ();
+    type OrderId = u64;
+    type Transaction = ();
+
+    fn create(&self, _: &mut (), link: &OrderLink<u64>) -> Result<(), ()> {
+        self.0.borrow_mut().push(link.clone());
+        Ok(())
+    }
+
+    fn delete_by_order_id(&self, _: &mut (), order_id: &u64) -> Result<(), ()> {
+        self.0
+            .borrow_mut()
+            .retain(|link| link.order_id != *order_id);
+        Ok(())
+    }
+
+    fn get_by_order_id(&self, _: &mut (), order_id: &u64) -> Result<Option<OrderLink<u64>>, ()> {
+        Ok(self
+            .0
+            .borrow()
+            .iter()
+            .find(|link| link.order_id == *order_id)
+            .cloned())
+    }
+}
+
+#[derive(Default)]
+struct TestChildOrderRepository(RefCell<Vec<TestChildOrders>>);
+
+impl ChildOrderRepositoryLike for TestChildOrderRepository {
+    type Err = ();
+    type Asset = &'static str;
+    type OrderId = u64;
+    type Price = u64;
+    type Quantity = u64;
+    type Transaction = ();
+
+    fn create(&self, _: &mut (), child_orders: &TestChildOrders) -> Result<(), ()> {
+        let mut stored = self.0.borrow_mut();
+        stored.retain(|stored| stored.parent_id != child_orders.parent_id);
+        stored.push(child_orders.clone());
+        Ok(())
+    }
+
+    fn update(&self, tx: &mut (), child_orders: &TestChildOrders) -> Result<(), ()> {
+        self.create(tx, child_orders)
+    }
+
+    fn delete_by_parent_id(&self, _: &mut (), parent_id: &u64) -> Result<(), ()> {
+        self.0
+            .borrow_mut()
+            .retain(|child_orders| child_orders.parent_id != *parent_id);
+        Ok(())
+    }
+
+    fn get_by_parent_id(&self, _: &mut (), parent_id: &u64) -> Result<Option<TestChildOrders>, ()> {
+        Ok(self
+            .0
+            .borrow()
+            .iter()
+            .find(|child_orders| child_orders.parent_id == *parent_id)
+            .cloned())
+    }
+}
+
+struct TestTradingSchedule {
+    session_state: SessionState,
+    session_close_time_ms: Option<u64>,
+}
+
+impl TradingScheduleLike for TestTradingSchedule {
+    fn session_state(&self, _: u64) -> SessionState {
+        self.session_state
+    }
+
+    fn session_close_time_ms(&self, _: u64) -> Option<u64> {
+        self.session_close_time_ms
+    }
+}
+
+struct TestMatchingEngine {
+    asset_pair: AssetPair<&'static str>,
+    bid_limit_order_repository: TestLimitOrderRepository,
+    ask_limit_order_repository: TestLimitOrderRepository,
+    stop_order_repository: TestStopOrderRepository,
+    order_link_repository: TestOrderLinkRepository,
+    child_order_repository: TestChildOrderRepository,
+    trading_schedule: TestTradingSchedule,
+    matching_policy: MatchingPolicy<u64>,
+    self_trade_prevention: SelfTradePrevention,
+    now_ms: u64,
+    tick_size: u64,
+    lead_market_makers: Vec<u64>,
+}
+
+impl TestMatchingEngine {
+    fn new() -> Self {
+        Self {
+            asset_pair: AssetPair::new("BASE", "QUOTE"),
+            bid_limit_order_repository: TestLimitOrderRepository::new(OrderSide::Bid),
+            ask_limit_order_repository: TestLimitOrderRepository::new(OrderSide::Ask),
+            stop_order_repository: TestStopOrderRepository::default(),
+            order_link_repository: TestOrderLinkRepository::default(),
+            child_order_repository: TestChildOrderRepository::default(),
+            trading_schedule: TestTradingSchedule {
+                session_state: SessionState::Continuous,
+                session_close_time_ms: None,
+            },
+            matching_policy: MatchingPolicy::Fifo,
+            self_trade_prevention: SelfTradePrevention::CancelNewest,
+            now_ms: 0,
+            tick_size: 1,
+            lead_market_makers: vec![],
+        }
+    }
+
+    fn process(
+        &mut self,
+        order_request: OrderRequest<u64, &'static str, u64, u64>,
+    ) -> OrderProcessingResult<u64, u64, u64> {
+        self.process_order(&mut (), order_request).unwrap()
+    }
+
+    fn limit_order(&self, side: OrderSide, id: u64) -> Option<TestLimitOrder> {
+        match side {
+            OrderSide::Bid => self
+                .bid_limit_order_repository
+                .get_by_order_id(&mut (), &id),
+            OrderSide::Ask => self
+                .ask_limit_order_repository
+                .get_by_order_id(&mut (), &id),
+        }
+        .unwrap()
+    }
+}
+
+impl MatchingEngine for TestMatchingEngine {
+    type Err = ();
+    type Asset = &'static str;
+    type OrderId = u64;
+    type Price = u64;
+    type Quantity = u64;
+    type Transaction = ();
+    type BidLimitOrderRepository = TestLimitOrderRepository;
+    type AskLimitOrderRepository = TestLimitOrderRepository;
+    type StopOrderRepository = TestStopOrderRepository;
+    type OrderLinkRepository = TestOrderLinkRepository;
+    type ChildOrderRepository = TestChildOrderRepository;
+    type TradingSchedule = TestTradingSchedule;
+
+    fn asset_pair(&self) -> &AssetPair<&'static str> {
+        &self.asset_pair
+    }
+
+    fn bid_limit_order_repository(&self) -> &TestLimitOrderRepository {
+        &self.bid_limit_order_repository
+    }
+
+    fn ask_limit_order_repository(&self) -> &TestLimitOrderRepository {
+        &self.ask_limit_order_repository
+    }
+
+    fn stop_order_repository(&self) -> &TestStopOrderRepository {
+        &self.stop_order_repository
+    }
+
+    fn order_link_repository(&self) -> &TestOrderLinkRepository {
+        &self.order_link_repository
+    }
+
+    fn child_order_repository(&self) -> &TestChildOrderRepository {
+        &self.child_order_repository
+    }
+
+    fn trading_schedule(&self) -> &TestTradingSchedule {
+        &self.trading_schedule
+    }
+
+    fn tick_size(&self) -> u64 {
+        self.tick_size
+    }
+
+    fn current_timestamp_ms(&self) -> u64 {
+        self.now_ms
+    }
+
+    fn matching_policy(&self) -> MatchingPolicy<u64> {
+        self.matching_policy
+    }
+
+    fn self_trade_prevention(&self) -> SelfTradePrevention {
+        self.self_trade_prevention
+    }
+
+    fn participant_role(&self, owner: Option<u64>) -> ParticipantRole {
+        match owner {
+            Some(owner) if self.lead_market_makers.contains(&owner) => {
+                ParticipantRole::LeadMarketMaker
+            }
+            _ => ParticipantRole::Regular,
+        }
+    }
+}
+
+fn limit(
+    id: u64,
+    side: OrderSide,
+    price: u64,
+    quantity: u64,
+) -> OrderRequest<u64, &'static str, u64, u64> {
+    OrderRequest::new_limit(
+        id,
+        AssetPair::new("BASE", "QUOTE"),
+        side,
+        price,
+        quantity,
+        TimeInForce::GTC,
+        id,
+    )
+}
+
+/// a GTC bracket order whose take-profit and stop-loss orders get the ids following `id`.
+fn bracket(
+    id: u64,
+    side: OrderSide,
+    price: u64,
+    quantity: u64,
+    take_profit_price: u64,
+    stop_price: u64,
+) -> OrderRequest<u64, &'static str, u64, u64> {
+    let entry_order = match limit(id, side, price, quantity) {
+        OrderRequest::Limit(limit_order) => limit_order,
+        _ => unreachable!(),
+    };
+    let take_profit_order = match limit(id + 1, side.opposite(), take_profit_price, quantity) {
+        OrderRequest::Limit(limit_order) => limit_order,
+        _ => unreachable!(),
+    };
+    let stop_loss_order = match OrderRequest::new_stop_loss(
+        id + 2,
+        AssetPair::new("BASE", "QUOTE"),
+        side.opposite(),
+        stop_price,
+        quantity,
+        id + 2,
+    ) {
+        OrderRequest::Stop(stop_order) => stop_order,
+        _ => unreachable!(),
+    };
+    OrderRequest::new_bracket(entry_order, take_profit_order, stop_loss_order)
+}
+
+fn amend(
+    id: u64,
+    target_id: u64,
+    side: OrderSide,
+    price: u64,
+    quantity: u64,
+) -> OrderRequest<u64, &'static str, u64, u64> {
+    OrderRequest::new_amend(
+        id,
+        AssetPair::new("BASE", "QUOTE"),
+        target_id,
+        OrderType::Limit,
+        side,
+        price,
+        quantity,
+        id,
+    )
+}
+
+/// whether `results` cancels the take-profit and stop-loss orders of the bracket order `id`.
+fn cancels_child_orders(results: &OrderProcessingResult<u64, u64, u64>, id: u64) -> bool {
+    [id + 1, id + 2].iter().all(|child_id| {
+        results.iter().any(|result| {
+            matches!(
+                result,
+                Ok(MatchingEngineOutput::Cancelled { id: parent_id, target_id, .. })
+                    if *parent_id == id && target_id == child_id
+            )
+        })
+    })
+}
+
+#[test]
+fn bracket_order_cancelled_by_self_trade_prevention_cancels_its_child_orders() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Ask, 100, 10).with_owner(1));
+
+    let results = engine.process(bracket(2, OrderSide::Bid, 100, 10, 110, 90).with_owner(1));
+
+    assert!(cancels_child_orders(&results, 2));
+    assert!(engine
+        .child_order_repository
+        .get_by_parent_id(&mut (), &2)
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn resting_bracket_order_cancelled_by_self_trade_prevention_cancels_its_child_orders() {
+    let mut engine = TestMatchingEngine::new();
+    engine.self_trade_prevention = SelfTradePrevention::CancelOldest;
+    engine.process(bracket(1, OrderSide::Bid, 100, 10, 110, 90).with_owner(1));
+
+    let results = engine.process(limit(4, OrderSide::Ask, 100, 10).with_owner(1));
+
+    assert!(cancels_child_orders(&results, 1));
+    assert!(engine
+        .child_order_repository
+        .get_by_parent_id(&mut (), &1)
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn amend_unfilled_order_to_zero_cancels_it() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Bid, 100, 10));
+
+    let results = engine.process(amend(2, 1, OrderSide::Bid, 100, 0));
+
+    assert!(matches!(
+        results.as_slice(),
+        [Ok(MatchingEngineOutput::Cancelled {
+            id: 2,
+            target_id: 1,
+            ..
+        })]
+    ));
+    assert!(engine.limit_order(OrderSide::Bid, 1).is_none());
+}
+
+#[test]
+fn amend_crossing_the_spread_trades_and_rests_the_remainder() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Ask, 101, 3));
+    engine.process(limit(2, OrderSide::Ask, 102, 3));
+    engine.process(limit(3, OrderSide::Bid, 99, 5));
+
+    let results = engine.process(amend(4, 3, OrderSide::Bid, 101, 5));
+
+    assert!(matches!(
+        results.first(),
+        Some(Ok(MatchingEngineOutput::Amended {
+            id: 4,
+            target_id: 3,
+            price: 101,
+            quantity: 5,
+            ..
+        }))
+    ));
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::PartiallyFilled {
+            id: 3,
+            price: 101,
+            quantity: 3,
+            ..
+        })
+    )));
+    assert!(results
+        .iter()
+        .any(|result| matches!(result, Ok(MatchingEngineOutput::Filled { id: 1, .. }))));
+    assert!(engine.limit_order(OrderSide::Ask, 1).is_none());
+    let amended_order = engine.limit_order(OrderSide::Bid, 3).unwrap();
+    assert_eq!(amended_order.price, 101);
+    assert_eq!(amended_order.quantity, 2);
+    assert_eq!(engine.limit_order(OrderSide::Ask, 2).unwrap().quantity, 3);
+}
+
+#[test]
+fn amend_crossing_order_to_zero_cancels_it_without_fills() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Bid, 100, 10));
+    engine.process(limit(2, OrderSide::Ask, 101, 10));
+
+    let results = engine.process(amend(3, 1, OrderSide::Bid, 101, 0));
+
+    assert!(matches!(
+        results.as_slice(),
+        [Ok(MatchingEngineOutput::Cancelled {
+            id: 3,
+            target_id: 1,
+            ..
+        })]
+    ));
+    assert!(engine.limit_order(OrderSide::Bid, 1).is_none());
+    assert_eq!(engine.limit_order(OrderSide::Ask, 2).unwrap().quantity, 10);
+}
+
+#[test]
+fn amend_to_filled_quantity_cancels_the_remainder() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Bid, 100, 10));
+    engine.process(limit(2, OrderSide::Ask, 100, 4));
+
+    let results = engine.process(amend(3, 1, OrderSide::Bid, 100, 4));
+
+    assert!(matches!(
+        results.as_slice(),
+        [Ok(MatchingEngineOutput::Cancelled {
+            id: 3,
+            target_id: 1,
+            ..
+        })]
+    ));
+    assert!(engine.limit_order(OrderSide::Bid, 1).is_none());
+}
+
+#[test]
+fn amend_below_filled_quantity_is_rejected() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Bid, 100, 10));
+    engine.process(limit(2, OrderSide::Ask, 100, 4));
+
+    let results = engine.process(amend(3, 1, OrderSide::Bid, 100, 3));
+
+    assert!(matches!(
+        results.as_slice(),
+        [Err(MatchingEngineFailure::AmendBelowFilledQuantity {
+            order_id: 3,
+            target_order_id: 1,
+        })]
+    ));
+    assert_eq!(engine.limit_order(OrderSide::Bid, 1).unwrap().quantity, 6);
+}
+
+#[test]
+fn amend_size_down_keeps_the_filled_quantity() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Bid, 100, 10));
+    engine.process(limit(2, OrderSide::Ask, 100, 4));
+
+    let results = engine.process(amend(3, 1, OrderSide::Bid, 100, 5));
+
+    assert!(matches!(
+        results.as_slice(),
+        [Ok(MatchingEngineOutput::Amended {
+            id: 3,
+            target_id: 1,
+            quantity: 5,
+            priority_retained: true,
+            ..
+        })]
+    ));
+    let order = engine.limit_order(OrderSide::Bid, 1).unwrap();
+    assert_eq!(order.quantity, 1);
+    assert_eq!(order.filled_quantity, Some(4));
+    assert_eq!(order.original_quantity, 5);
+}
+
+#[test]
+fn amend_size_up_sets_the_new_size_of_the_order() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Bid, 100, 10));
+    engine.process(limit(2, OrderSide::Ask, 100, 4));
+
+    engine.process(amend(3, 1, OrderSide::Bid, 100, 20));
+
+    let order = engine.limit_order(OrderSide::Bid, 1).unwrap();
+    assert_eq!(order.quantity, 16);
+    assert_eq!(order.filled_quantity, Some(4));
+    assert_eq!(order.original_quantity, 20);
+}
+
+#[test]
+fn amend_stop_order_to_zero_cancels_it() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(OrderRequest::new_stop_loss(
+        1,
+        AssetPair::new("BASE", "QUOTE"),
+        OrderSide::Ask,
+        90,
+        10,
+        1,
+    ));
+
+    let results = engine.process(OrderRequest::new_amend(
+        2,
+        AssetPair::new("BASE", "QUOTE"),
+        1,
+        OrderType::StopLoss,
+        OrderSide::Ask,
+        90,
+        0,
+        2,
+    ));
+
+    assert!(matches!(
+        results.as_slice(),
+        [Ok(MatchingEngineOutput::Cancelled {
+            id: 2,
+            target_id: 1,
+            ..
+        })]
+    ));
+    assert!(engine
+        .stop_order_repository
+        .get_by_order_id(&mut (), &1)
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn pro_rata_fill_cancels_only_the_orders_linked_to_filled_orders() {
+    let mut engine = TestMatchingEngine::new();
+    engine.matching_policy = MatchingPolicy::ProRata {
+        minimum_allocation: None,
+        lot_size: 1,
+    };
+    engine.process(limit(1, OrderSide::Ask, 100, 10));
+    let limit_order = match limit(2, OrderSide::Ask, 100, 1) {
+        OrderRequest::Limit(limit_order) => limit_order,
+        _ => unreachable!(),
+    };
+    let stop_order = match OrderRequest::new_stop_loss(
+        3,
+        AssetPair::new("BASE", "QUOTE"),
+        OrderSide::Ask,
+        90,
+        1,
+        2,
+    ) {
+        OrderRequest::Stop(stop_order) => stop_order,
+        _ => unreachable!(),
+    };
+    engine.process(OrderRequest::new_oco(limit_order, stop_order));
+
+    // the rounding leaves the whole quantity to the oldest order
+    let results = engine.process(limit(4, OrderSide::Bid, 100, 1));
+
+    assert!(!results
+        .iter()
+        .any(|result| matches!(result, Ok(MatchingEngineOutput::Cancelled { .. }))));
+    assert_eq!(engine.limit_order(OrderSide::Ask, 1).unwrap().quantity, 9);
+    assert!(engine.limit_order(OrderSide::Ask, 2).is_some());
+    assert!(engine
+        .stop_order_repository
+        .get_by_order_id(&mut (), &3)
+        .unwrap()
+        .is_some());
+}
+
+#[test]
+fn fill_or_kill_does_not_count_orders_of_the_same_owner() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Ask, 100, 5).with_owner(7));
+    engine.process(limit(2, OrderSide::Ask, 100, 5).with_owner(8));
+    let fill_or_kill = OrderRequest::new_limit(
+        3,
+        AssetPair::new("BASE", "QUOTE"),
+        OrderSide::Bid,
+        100,
+        10,
+        TimeInForce::FOK,
+        3,
+    );
+
+    let results = engine.process(fill_or_kill.with_owner(7));
+
+    assert!(!results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::Filled { .. }
+            | MatchingEngineOutput::PartiallyFilled { .. }
+            | MatchingEngineOutput::SelfTradePrevented { .. })
+    )));
+    assert_eq!(engine.limit_order(OrderSide::Ask, 1).unwrap().quantity, 5);
+    assert_eq!(engine.limit_order(OrderSide::Ask, 2).unwrap().quantity, 5);
+}
+
+fn fill_or_kill(
+    id: u64,
+    side: OrderSide,
+    price: u64,
+    quantity: u64,
+) -> OrderRequest<u64, &'static str, u64, u64> {
+    OrderRequest::new_limit(
+        id,
+        AssetPair::new("BASE", "QUOTE"),
+        side,
+        price,
+        quantity,
+        TimeInForce::FOK,
+        id,
+    )
+}
+
+#[test]
+fn fill_or_kill_is_killed_by_an_order_of_the_same_owner_cancelling_it() {
+    for mode in [
+        SelfTradePrevention::CancelNewest,
+        SelfTradePrevention::CancelBoth,
+        SelfTradePrevention::DecrementAndCancel,
+    ] {
+        let mut engine = TestMatchingEngine::new();
+        engine.self_trade_prevention = mode;
+        engine.process(limit(1, OrderSide::Ask, 100, 5).with_owner(8));
+        engine.process(limit(2, OrderSide::Ask, 101, 5).with_owner(7));
+        engine.process(limit(3, OrderSide::Ask, 102, 5).with_owner(8));
+
+        let results = engine.process(fill_or_kill(4, OrderSide::Bid, 102, 10).with_owner(7));
+
+        assert!(!results.iter().any(|result| matches!(
+            result,
+            Ok(MatchingEngineOutput::Filled { .. }
+                | MatchingEngineOutput::PartiallyFilled { .. }
+                | MatchingEngineOutput::SelfTradePrevented { .. })
+        )));
+        assert!(results.iter().any(|result| matches!(
+            result,
+            Ok(MatchingEngineOutput::Expired {
+                id: 4,
+                quantity: 10,
+                ..
+            })
+        )));
+        assert_eq!(engine.limit_order(OrderSide::Ask, 1).unwrap().quantity, 5);
+        assert_eq!(engine.limit_order(OrderSide::Ask, 2).unwrap().quantity, 5);
+    }
+}
+
+#[test]
+fn fill_or_kill_trades_through_the_cancelled_oldest_order_of_the_same_owner() {
+    let mut engine = TestMatchingEngine::new();
+    engine.self_trade_prevention = SelfTradePrevention::CancelOldest;
+    engine.process(limit(1, OrderSide::Ask, 100, 5).with_owner(8));
+    engine.process(limit(2, OrderSide::Ask, 101, 5).with_owner(7));
+    engine.process(limit(3, OrderSide::Ask, 102, 5).with_owner(8));
+
+    let results = engine.process(fill_or_kill(4, OrderSide::Bid, 102, 10).with_owner(7));
+
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::Filled {
+            id: 4,
+            price: 102,
+            quantity: 5,
+            ..
+        })
+    )));
+    assert!(engine.limit_order(OrderSide::Ask, 2).is_none());
+}
+
+#[test]
+fn pro_rata_fill_or_kill_is_killed_by_an_order_of_the_same_owner_at_the_level() {
+    let mut engine = TestMatchingEngine::new();
+    engine.matching_policy = MatchingPolicy::ProRata {
+        minimum_allocation: None,
+        lot_size: 1,
+    };
+    engine.process(limit(1, OrderSide::Ask, 100, 10).with_owner(8));
+    engine.process(limit(2, OrderSide::Ask, 100, 5).with_owner(7));
+
+    let results = engine.process(fill_or_kill(3, OrderSide::Bid, 100, 10).with_owner(7));
+
+    assert!(!results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::Filled { .. }
+            | MatchingEngineOutput::PartiallyFilled { .. }
+            | MatchingEngineOutput::SelfTradePrevented { .. })
+    )));
+    assert_eq!(engine.limit_order(OrderSide::Ask, 1).unwrap().quantity, 10);
+    assert_eq!(engine.limit_order(OrderSide::Ask, 2).unwrap().quantity, 5);
+}
+
+#[test]
+fn pro_rata_applies_self_trade_prevention_to_the_whole_level() {
+    let mut engine = TestMatchingEngine::new();
+    engine.matching_policy = MatchingPolicy::ProRata {
+        minimum_allocation: None,
+        lot_size: 1,
+    };
+    engine.process(limit(1, OrderSide::Ask, 100, 5).with_owner(8));
+    engine.process(limit(2, OrderSide::Ask, 100, 5).with_owner(7));
+
+    let results = engine.process(limit(3, OrderSide::Bid, 100, 4).with_owner(7));
+
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::SelfTradePrevented {
+            id: 3,
+            opposite_id: 2,
+            mode: SelfTradePrevention::CancelNewest,
+            quantity: Some(4),
+            opposite_quantity: None,
+            ..
+        })
+    )));
+    assert!(!results
+        .iter()
+        .any(|result| matches!(result, Ok(MatchingEngineOutput::Filled { .. }))));
+    assert_eq!(engine.limit_order(OrderSide::Ask, 1).unwrap().quantity, 5);
+    assert_eq!(engine.limit_order(OrderSide::Ask, 2).unwrap().quantity, 5);
+    assert!(engine.limit_order(OrderSide::Bid, 3).is_none());
+}
+
+#[test]
+fn pro_rata_cancels_the_oldest_order_of_the_same_owner_before_allocating() {
+    let mut engine = TestMatchingEngine::new();
+    engine.matching_policy = MatchingPolicy::ProRata {
+        minimum_allocation: None,
+        lot_size: 1,
+    };
+    engine.self_trade_prevention = SelfTradePrevention::CancelOldest;
+    engine.process(limit(1, OrderSide::Ask, 100, 5).with_owner(8));
+    engine.process(limit(2, OrderSide::Ask, 100, 5).with_owner(7));
+
+    let results = engine.process(limit(3, OrderSide::Bid, 100, 4).with_owner(7));
+
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::SelfTradePrevented {
+            id: 3,
+            opposite_id: 2,
+            quantity: None,
+            opposite_quantity: Some(5),
+            ..
+        })
+    )));
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::Filled {
+            id: 3,
+            quantity: 4,
+            ..
+        })
+    )));
+    assert_eq!(engine.limit_order(OrderSide::Ask, 1).unwrap().quantity, 1);
+    assert!(engine.limit_order(OrderSide::Ask, 2).is_none());
+}
+
+#[test]
+fn market_to_limit_fills_and_remainder_keep_the_order_type() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Ask, 100, 4));
+    engine.process(limit(2, OrderSide::Ask, 101, 4));
+
+    let results = engine.process(OrderRequest::new_market_to_limit(
+        3,
+        AssetPair::new("BASE", "QUOTE"),
+        OrderSide::Bid,
+        10,
+        3,
+    ));
+
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::PartiallyFilled {
+            id: 3,
+            order_type: OrderType::MarketToLimit,
+            price: 100,
+            quantity: 4,
+            ..
+        })
+    )));
+    let remainder = engine.limit_order(OrderSide::Bid, 3).unwrap();
+    assert_eq!(remainder.order_type(), OrderType::MarketToLimit);
+    assert_eq!(remainder.price, 100);
+    assert_eq!(remainder.quantity, 6);
+
+    let results = engine.process(OrderRequest::new_cancel(
+        4,
+        AssetPair::new("BASE", "QUOTE"),
+        3,
+        OrderType::MarketToLimit,
+        OrderSide::Bid,
+    ));
+
+    assert!(matches!(
+        results.as_slice(),
+        [Ok(MatchingEngineOutput::Cancelled {
+            id: 4,
+            target_id: 3,
+            ..
+        })]
+    ));
+    assert!(engine.limit_order(OrderSide::Bid, 3).is_none());
+}
+
+#[test]
+fn immediate_or_cancel_order_expires_its_unfilled_remainder() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Ask, 100, 5));
+
+    let results = engine.process(OrderRequest::new_limit(
+        2,
+        AssetPair::new("BASE", "QUOTE"),
+        OrderSide::Bid,
+        100,
+        8,
+        TimeInForce::IOC,
+        2,
+    ));
+
+    assert!(matches!(
+        results.as_slice(),
+        [
+            Ok(MatchingEngineOutput::Accepted { id: 2, .. }),
+            Ok(MatchingEngineOutput::PartiallyFilled {
+                id: 2,
+                quantity: 5,
+                ..
+            }),
+            Ok(MatchingEngineOutput::Filled { id: 1, .. }),
+            Ok(MatchingEngineOutput::Expired {
+                id: 2,
+                quantity: 3,
+                ..
+            })
+        ]
+    ));
+    assert!(engine.limit_order(OrderSide::Bid, 2).is_none());
+}
+
+#[test]
+fn fill_or_kill_order_without_enough_liquidity_does_not_trade() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Ask, 100, 5));
+
+    let results = engine.process(fill_or_kill(2, OrderSide::Bid, 100, 8));
+
+    assert!(matches!(
+        results.as_slice(),
+        [
+            Ok(MatchingEngineOutput::Accepted { id: 2, .. }),
+            Ok(MatchingEngineOutput::Expired {
+                id: 2,
+                quantity: 8,
+                ..
+            })
+        ]
+    ));
+    assert_eq!(engine.limit_order(OrderSide::Ask, 1).unwrap().quantity, 5);
+
+    let results = engine.process(fill_or_kill(3, OrderSide::Bid, 100, 5));
+
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::Filled {
+            id: 3,
+            quantity: 5,
+            ..
+        })
+    )));
+    assert!(engine.limit_order(OrderSide::Ask, 1).is_none());
+}
+
+#[test]
+fn good_till_cancelled_order_rests_its_unfilled_remainder() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Ask, 100, 5));
+
+    let results = engine.process(limit(2, OrderSide::Bid, 100, 8));
+
+    assert!(!results
+        .iter()
+        .any(|result| matches!(result, Ok(MatchingEngineOutput::Expired { .. }))));
+    assert_eq!(engine.limit_order(OrderSide::Bid, 2).unwrap().quantity, 3);
+}
+
+fn stop_loss(
+    id: u64,
+    side: OrderSide,
+    stop_price: u64,
+    quantity: u64,
+) -> OrderRequest<u64, &'static str, u64, u64> {
+    OrderRequest::new_stop_loss(
+        id,
+        AssetPair::new("BASE", "QUOTE"),
+        side,
+        stop_price,
+        quantity,
+        id,
+    )
+}
+
+fn market(id: u64, side: OrderSide, quantity: u64) -> OrderRequest<u64, &'static str, u64, u64> {
+    OrderRequest::new_market(id, AssetPair::new("BASE", "QUOTE"), side, quantity, id)
+}
+
+#[test]
+fn stop_loss_order_is_triggered_by_the_last_trade_price() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Bid, 96, 5));
+    engine.process(limit(2, OrderSide::Bid, 95, 5));
+    engine.process(limit(3, OrderSide::Bid, 90, 10));
+    engine.process(stop_loss(4, OrderSide::Ask, 95, 4));
+
+    // a trade above the stop price leaves the order in the trigger book
+    let results = engine.process(market(5, OrderSide::Ask, 5));
+    assert!(!results
+        .iter()
+        .any(|result| matches!(result, Ok(MatchingEngineOutput::Triggered { .. }))));
+
+    let results = engine.process(market(6, OrderSide::Ask, 5));
+
+    let triggered = results.iter().position(|result| {
+        matches!(
+            result,
+            Ok(MatchingEngineOutput::Triggered {
+                id: 4,
+                order_type: OrderType::StopLoss,
+                stop_price: 95,
+                ..
+            })
+        )
+    });
+    let filled = results.iter().position(|result| {
+        matches!(
+            result,
+            Ok(MatchingEngineOutput::Filled {
+                id: 4,
+                order_type: OrderType::Market,
+                price: 90,
+                quantity: 4,
+                ..
+            })
+        )
+    });
+    assert!(triggered.unwrap() < filled.unwrap());
+    assert!(engine
+        .stop_order_repository
+        .get_by_order_id(&mut (), &4)
+        .unwrap()
+        .is_none());
+    assert_eq!(engine.limit_order(OrderSide::Bid, 3).unwrap().quantity, 6);
+}
+
+#[test]
+fn triggered_stop_loss_limit_order_rests_its_unfilled_remainder() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Bid, 95, 5));
+    engine.process(limit(2, OrderSide::Bid, 90, 10));
+    engine.process(OrderRequest::new_stop_loss_limit(
+        3,
+        AssetPair::new("BASE", "QUOTE"),
+        OrderSide::Ask,
+        95,
+        92,
+        4,
+        3,
+    ));
+
+    let results = engine.process(market(4, OrderSide::Ask, 5));
+
+    assert!(results
+        .iter()
+        .any(|result| matches!(result, Ok(MatchingEngineOutput::Triggered { id: 3, .. }))));
+    let stop_limit_order = engine.limit_order(OrderSide::Ask, 3).unwrap();
+    assert_eq!(stop_limit_order.price, 92);
+    assert_eq!(stop_limit_order.quantity, 4);
+    assert_eq!(engine.limit_order(OrderSide::Bid, 2).unwrap().quantity, 10);
+}
+
+#[test]
+fn fills_of_a_triggered_stop_order_trigger_the_next_stop_orders() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Bid, 95, 5));
+    engine.process(limit(2, OrderSide::Bid, 90, 5));
+    engine.process(limit(3, OrderSide::Bid, 85, 5));
+    engine.process(stop_loss(4, OrderSide::Ask, 95, 5));
+    engine.process(stop_loss(5, OrderSide::Ask, 90, 2));
+
+    let results = engine.process(market(6, OrderSide::Ask, 5));
+
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::Filled {
+            id: 4,
+            price: 90,
+            quantity: 5,
+            ..
+        })
+    )));
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::Filled {
+            id: 5,
+            price: 85,
+            quantity: 2,
+            ..
+        })
+    )));
+    assert_eq!(engine.limit_order(OrderSide::Bid, 3).unwrap().quantity, 3);
+}
+
+#[test]
+fn take_profit_order_is_triggered_when_the_price_rises_and_cascades_into_stop_orders() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Ask, 100, 5));
+    engine.process(limit(2, OrderSide::Ask, 105, 5));
+    engine.process(limit(3, OrderSide::Bid, 99, 10));
+    engine.process(OrderRequest::new_take_profit(
+        4,
+        AssetPair::new("BASE", "QUOTE"),
+        OrderSide::Ask,
+        105,
+        4,
+        4,
+    ));
+    engine.process(stop_loss(5, OrderSide::Ask, 99, 2));
+
+    let results = engine.process(market(6, OrderSide::Bid, 5));
+    assert!(!results
+        .iter()
+        .any(|result| matches!(result, Ok(MatchingEngineOutput::Triggered { .. }))));
+
+    let results = engine.process(market(7, OrderSide::Bid, 5));
+
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::Triggered {
+            id: 4,
+            order_type: OrderType::TakeProfit,
+            ..
+        })
+    )));
+    // the sale of the take-profit order at 99 triggers the stop-loss order
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::Filled {
+            id: 4,
+            price: 99,
+            quantity: 4,
+            ..
+        })
+    )));
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::Filled {
+            id: 5,
+            price: 99,
+            quantity: 2,
+            ..
+        })
+    )));
+    assert_eq!(engine.limit_order(OrderSide::Bid, 3).unwrap().quantity, 4);
+}
+
+#[test]
+fn take_profit_limit_order_is_amended_and_cancelled_in_the_trigger_book() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(OrderRequest::new_take_profit_limit(
+        1,
+        AssetPair::new("BASE", "QUOTE"),
+        OrderSide::Ask,
+        105,
+        104,
+        4,
+        1,
+    ));
+
+    let results = engine.process(OrderRequest::new_amend_stop(
+        2,
+        AssetPair::new("BASE", "QUOTE"),
+        1,
+        OrderType::TakeProfitLimit,
+        OrderSide::Ask,
+        110,
+        109,
+        6,
+        2,
+    ));
+
+    assert!(matches!(
+        results.as_slice(),
+        [Ok(MatchingEngineOutput::Amended {
+            id: 2,
+            target_id: 1,
+            priority_retained: false,
+            ..
+        })]
+    ));
+    let take_profit_order = engine
+        .stop_order_repository
+        .get_by_order_id(&mut (), &1)
+        .unwrap()
+        .unwrap();
+    assert_eq!(take_profit_order.stop_price, 110);
+    assert_eq!(take_profit_order.price, Some(109));
+    assert_eq!(take_profit_order.quantity, 6);
+
+    let results = engine.process(OrderRequest::new_cancel(
+        3,
+        AssetPair::new("BASE", "QUOTE"),
+        1,
+        OrderType::TakeProfitLimit,
+        OrderSide::Ask,
+    ));
+
+    assert!(matches!(
+        results.as_slice(),
+        [Ok(MatchingEngineOutput::Cancelled {
+            id: 3,
+            target_id: 1,
+            ..
+        })]
+    ));
+    assert!(engine
+        .stop_order_repository
+        .get_by_order_id(&mut (), &1)
+        .unwrap()
+        .is_none());
+}
+
+fn trailing_stop(
+    id: u64,
+    side: OrderSide,
+    stop_price: u64,
+    offset: TrailingOffset<u64>,
+    quantity: u64,
+) -> OrderRequest<u64, &'static str, u64, u64> {
+    OrderRequest::new_trailing_stop(
+        id,
+        AssetPair::new("BASE", "QUOTE"),
+        side,
+        stop_price,
+        offset,
+        quantity,
+        id,
+    )
+}
+
+#[test]
+fn trailing_stop_order_follows_the_high_water_mark_of_the_trades() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Ask, 100, 2));
+    engine.process(limit(2, OrderSide::Bid, 98, 2));
+    engine.process(limit(3, OrderSide::Bid, 95, 10));
+    engine.process(trailing_stop(
+        4,
+        OrderSide::Ask,
+        90,
+        TrailingOffset::Absolute(5),
+        3,
+    ));
+
+    let results = engine.process(market(5, OrderSide::Bid, 2));
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::TrailingStopMoved {
+            id: 4,
+            stop_price: 95,
+            ..
+        })
+    )));
+
+    // the stop price never moves down with the trades
+    let results = engine.process(market(6, OrderSide::Ask, 2));
+    assert!(!results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::TrailingStopMoved { .. } | MatchingEngineOutput::Triggered { .. })
+    )));
+    assert_eq!(
+        engine
+            .stop_order_repository
+            .get_by_order_id(&mut (), &4)
+            .unwrap()
+            .unwrap()
+            .stop_price,
+        95
+    );
+
+    let results = engine.process(market(7, OrderSide::Ask, 1));
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::Triggered {
+            id: 4,
+            order_type: OrderType::TrailingStop,
+            stop_price: 95,
+            ..
+        })
+    )));
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::Filled {
+            id: 4,
+            price: 95,
+            quantity: 3,
+            ..
+        })
+    )));
+    assert_eq!(engine.limit_order(OrderSide::Bid, 3).unwrap().quantity, 6);
+}
+
+#[test]
+fn trailing_stop_buy_order_follows_the_low_water_mark_by_basis_points() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Ask, 100, 1));
+    engine.process(limit(2, OrderSide::Ask, 104, 1));
+    engine.process(limit(3, OrderSide::Ask, 105, 10));
+    engine.process(trailing_stop(
+        4,
+        OrderSide::Bid,
+        200,
+        TrailingOffset::BasisPoints(500),
+        2,
+    ));
+
+    let results = engine.process(market(5, OrderSide::Bid, 1));
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::TrailingStopMoved {
+            id: 4,
+            stop_price: 105,
+            ..
+        })
+    )));
+
+    let results = engine.process(market(6, OrderSide::Bid, 1));
+    assert!(!results
+        .iter()
+        .any(|result| matches!(result, Ok(MatchingEngineOutput::Triggered { .. }))));
+
+    let results = engine.process(market(7, OrderSide::Bid, 1));
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::Filled {
+            id: 4,
+            price: 105,
+            quantity: 2,
+            ..
+        })
+    )));
+    assert_eq!(engine.limit_order(OrderSide::Ask, 3).unwrap().quantity, 7);
+}
+
+#[test]
+fn iceberg_order_replenishes_its_peak_behind_the_orders_at_its_price() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(OrderRequest::new_iceberg(
+        1,
+        AssetPair::new("BASE", "QUOTE"),
+        OrderSide::Ask,
+        100,
+        10,
+        4,
+        1,
+    ));
+    engine.process(limit(2, OrderSide::Ask, 100, 5));
+    // only the peak is displayed in the book
+    let iceberg_order = engine.limit_order(OrderSide::Ask, 1).unwrap();
+    assert_eq!(iceberg_order.quantity, 4);
+    assert_eq!(iceberg_order.remaining_quantity(), 10);
+
+    engine.now_ms = 10;
+    let results = engine.process(market(3, OrderSide::Bid, 4));
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::PartiallyFilled {
+            id: 1,
+            quantity: 4,
+            ..
+        })
+    )));
+    let iceberg_order = engine.limit_order(OrderSide::Ask, 1).unwrap();
+    assert_eq!(iceberg_order.quantity, 4);
+    assert_eq!(iceberg_order.remaining_quantity(), 6);
+
+    // the next peak has lost its time priority to the order behind it
+    let results = engine.process(market(4, OrderSide::Bid, 5));
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::Filled {
+            id: 2,
+            quantity: 5,
+            ..
+        })
+    )));
+    assert!(!results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::Filled { id: 1, .. }
+            | MatchingEngineOutput::PartiallyFilled { id: 1, .. })
+    )));
+
+    let results = engine.process(market(5, OrderSide::Bid, 6));
+    assert!(results
+        .iter()
+        .any(|result| matches!(result, Ok(MatchingEngineOutput::Filled { id: 1, .. }))));
+    assert!(engine.limit_order(OrderSide::Ask, 1).is_none());
+}
+
+#[test]
+fn protected_market_order_stops_at_its_slippage_limit() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Ask, 100, 3));
+    engine.process(limit(2, OrderSide::Ask, 101, 3));
+    engine.process(limit(3, OrderSide::Ask, 103, 5));
+
+    let results = engine.process(OrderRequest::new_protected_market(
+        4,
+        AssetPair::new("BASE", "QUOTE"),
+        OrderSide::Bid,
+        MarketOrderQuantity::Base(10),
+        PriceProtection::BasisPoints(100),
+        4,
+    ));
+
+    assert!(results
+        .iter()
+        .any(|result| matches!(result, Ok(MatchingEngineOutput::Filled { id: 2, .. }))));
+    assert!(!results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::Filled { id: 3, .. }
+            | MatchingEngineOutput::PartiallyFilled { id: 3, .. })
+    )));
+    assert!(matches!(
+        results.last(),
+        Some(Ok(MatchingEngineOutput::RemainderCancelled {
+            id: 4,
+            quantity: MarketOrderQuantity::Base(4),
+            ..
+        }))
+    ));
+    assert_eq!(engine.limit_order(OrderSide::Ask, 3).unwrap().quantity, 5);
+}
+
+#[test]
+fn protected_market_order_within_its_slippage_limit_is_filled() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Bid, 100, 3));
+    engine.process(limit(2, OrderSide::Bid, 98, 3));
+
+    let results = engine.process(OrderRequest::new_protected_market(
+        3,
+        AssetPair::new("BASE", "QUOTE"),
+        OrderSide::Ask,
+        MarketOrderQuantity::Base(5),
+        PriceProtection::Absolute(2),
+        3,
+    ));
+
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::Filled {
+            id: 3,
+            price: 98,
+            quantity: 2,
+            ..
+        })
+    )));
+    assert!(!results
+        .iter()
+        .any(|result| matches!(result, Ok(MatchingEngineOutput::RemainderCancelled { .. }))));
+}
+
+#[test]
+fn unmatched_immediate_or_cancel_order_expires_with_its_order_type() {
+    let mut engine = TestMatchingEngine::new();
+    let limit_maker = match OrderRequest::new_limit_maker(
+        1,
+        AssetPair::new("BASE", "QUOTE"),
+        OrderSide::Bid,
+        100,
+        10,
+        PostOnly::Reject,
+        1,
+    ) {
+        OrderRequest::Limit(limit_order) => limit_order,
+        _ => unreachable!(),
+    };
+    let mut results = vec![];
+
+    engine
+        .process_unmatched_limit_order(
+            &mut (),
+            &mut results,
+            &LimitOrder {
+                time_in_force: TimeInForce::IOC,
+                ..limit_maker
+            },
+        )
+        .unwrap();
+
+    assert!(matches!(
+        results.as_slice(),
+        [Ok(MatchingEngineOutput::Expired {
+            id: 1,
+            order_type: OrderType::LimitMaker,
+            quantity: 10,
+            ..
+        })]
+    ));
+}
+
+#[test]
+fn post_only_order_is_repriced_behind_the_best_price_including_hidden_orders() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(OrderRequest::new_hidden(
+        1,
+        AssetPair::new("BASE", "QUOTE"),
+        OrderSide::Ask,
+        100,
+        10,
+        1,
+    ));
+    engine.process(limit(2, OrderSide::Ask, 103, 10));
+
+    let results = engine.process(OrderRequest::new_limit_maker(
+        3,
+        AssetPair::new("BASE", "QUOTE"),
+        OrderSide::Bid,
+        103,
+        10,
+        PostOnly::Reprice,
+        3,
+    ));
+
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::Repriced {
+            id: 3,
+            price: 99,
+            ..
+        })
+    )));
+    assert_eq!(engine.limit_order(OrderSide::Bid, 3).unwrap().price, 99);
+    assert_eq!(engine.limit_order(OrderSide::Ask, 1).unwrap().quantity, 10);
+}
+
+#[test]
+fn post_only_order_which_cannot_be_repriced_above_zero_is_rejected() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Ask, 0, 10));
+
+    let results = engine.process(OrderRequest::new_limit_maker(
+        2,
+        AssetPair::new("BASE", "QUOTE"),
+        OrderSide::Bid,
+        0,
+        10,
+        PostOnly::Reprice,
+        2,
+    ));
+
+    assert!(matches!(
+        results.as_slice(),
+        [Err(MatchingEngineFailure::WouldTakeLiquidity(2))]
+    ));
+    assert!(engine.limit_order(OrderSide::Bid, 2).is_none());
+}
+
+#[test]
+fn pegged_order_offset_below_zero_has_no_price() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Bid, 3, 10));
+
+    let results = engine.process(OrderRequest::new_pegged(
+        2,
+        AssetPair::new("BASE", "QUOTE"),
+        OrderSide::Bid,
+        PegType::Primary,
+        Some(5),
+        None,
+        10,
+        2,
+    ));
+
+    assert!(matches!(
+        results.as_slice(),
+        [Err(MatchingEngineFailure::NoReferencePrice(2))]
+    ));
+    assert!(engine.limit_order(OrderSide::Bid, 2).is_none());
+}
+
+#[test]
+fn pegged_order_is_clamped_by_the_best_price_including_hidden_orders() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Bid, 100, 10));
+    engine.process(limit(2, OrderSide::Ask, 110, 10));
+    engine.process(OrderRequest::new_hidden(
+        3,
+        AssetPair::new("BASE", "QUOTE"),
+        OrderSide::Ask,
+        103,
+        10,
+        3,
+    ));
+
+    engine.process(OrderRequest::new_pegged(
+        4,
+        AssetPair::new("BASE", "QUOTE"),
+        OrderSide::Bid,
+        PegType::Midpoint,
+        None,
+        Some(200),
+        10,
+        4,
+    ));
+
+    assert_eq!(engine.limit_order(OrderSide::Bid, 4).unwrap().price, 102);
+}
+
+#[test]
+fn bracket_child_orders_are_submitted_after_the_entry_order_matching() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Ask, 100, 5));
+    engine.process(limit(2, OrderSide::Ask, 102, 5));
+    let entry_order = match limit(3, OrderSide::Bid, 102, 10) {
+        OrderRequest::Limit(limit_order) => limit_order,
+        _ => unreachable!(),
+    };
+    let take_profit_order = match limit(4, OrderSide::Ask, 101, 10) {
+        OrderRequest::Limit(limit_order) => limit_order,
+        _ => unreachable!(),
+    };
+    let stop_loss_order = match OrderRequest::new_stop_loss(
+        5,
+        AssetPair::new("BASE", "QUOTE"),
+        OrderSide::Ask,
+        90,
+        10,
+        5,
+    ) {
+        OrderRequest::Stop(stop_order) => stop_order,
+        _ => unreachable!(),
+    };
+
+    let results = engine.process(OrderRequest::new_bracket(
+        entry_order,
+        take_profit_order,
+        stop_loss_order,
+    ));
+
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::Filled {
+            id: 3,
+            price: 102,
+            quantity: 5,
+            ..
+        })
+    )));
+    assert!(engine.limit_order(OrderSide::Ask, 2).is_none());
+    assert_eq!(engine.limit_order(OrderSide::Ask, 4).unwrap().quantity, 10);
+}
+
+#[test]
+fn partially_filled_immediate_or_cancel_entry_order_submits_its_child_orders() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Ask, 100, 5));
+    let entry_order = match OrderRequest::new_limit(
+        2,
+        AssetPair::new("BASE", "QUOTE"),
+        OrderSide::Bid,
+        100,
+        10,
+        TimeInForce::IOC,
+        2,
+    ) {
+        OrderRequest::Limit(limit_order) => limit_order,
+        _ => unreachable!(),
+    };
+    let take_profit_order = match limit(3, OrderSide::Ask, 110, 10) {
+        OrderRequest::Limit(limit_order) => limit_order,
+        _ => unreachable!(),
+    };
+    let stop_loss_order = match OrderRequest::new_stop_loss(
+        4,
+        AssetPair::new("BASE", "QUOTE"),
+        OrderSide::Ask,
+        90,
+        10,
+        4,
+    ) {
+        OrderRequest::Stop(stop_order) => stop_order,
+        _ => unreachable!(),
+    };
+
+    let results = engine.process(OrderRequest::new_bracket(
+        entry_order,
+        take_profit_order,
+        stop_loss_order,
+    ));
+
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::Expired {
+            id: 2,
+            quantity: 5,
+            ..
+        })
+    )));
+    assert_eq!(engine.limit_order(OrderSide::Ask, 3).unwrap().quantity, 5);
+    assert_eq!(
+        engine
+            .stop_order_repository
+            .get_by_order_id(&mut (), &4)
+            .unwrap()
+            .unwrap()
+            .quantity,
+        5
+    );
+    assert!(engine
+        .child_order_repository
+        .get_by_parent_id(&mut (), &2)
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn child_orders_protect_the_fills_after_a_partial_fill_of_the_take_profit_order() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Ask, 100, 5));
+    engine.process(bracket(2, OrderSide::Bid, 100, 10, 110, 90));
+    // the partial fill of the take-profit order cancels the stop-loss order
+    engine.process(limit(5, OrderSide::Bid, 110, 2));
+    assert!(engine
+        .stop_order_repository
+        .get_by_order_id(&mut (), &4)
+        .unwrap()
+        .is_none());
+
+    let results = engine.process(limit(6, OrderSide::Ask, 100, 5));
+
+    assert!(results
+        .iter()
+        .any(|result| matches!(result, Ok(MatchingEngineOutput::Filled { id: 2, .. }))));
+    assert_eq!(engine.limit_order(OrderSide::Ask, 3).unwrap().quantity, 8);
+    assert_eq!(
+        engine
+            .stop_order_repository
+            .get_by_order_id(&mut (), &4)
+            .unwrap()
+            .unwrap()
+            .quantity,
+        8
+    );
+    assert!(engine
+        .order_link_repository
+        .get_by_order_id(&mut (), &3)
+        .unwrap()
+        .is_some());
+}
+
+#[test]
+fn increased_take_profit_order_loses_its_time_priority() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Ask, 100, 5));
+    engine.process(bracket(2, OrderSide::Bid, 100, 10, 110, 90));
+    engine.process(limit(5, OrderSide::Ask, 110, 5));
+    engine.now_ms = 100;
+    engine.process(limit(6, OrderSide::Ask, 100, 5));
+    assert_eq!(engine.limit_order(OrderSide::Ask, 3).unwrap().quantity, 10);
+
+    let results = engine.process(limit(7, OrderSide::Bid, 110, 5));
+
+    assert!(results
+        .iter()
+        .any(|result| matches!(result, Ok(MatchingEngineOutput::Filled { id: 5, .. }))));
+    assert_eq!(engine.limit_order(OrderSide::Ask, 3).unwrap().quantity, 10);
+}
+
+#[test]
+fn self_trade_prevention_of_an_oco_leg_cancels_the_linked_order() {
+    let mut engine = TestMatchingEngine::new();
+    engine.self_trade_prevention = SelfTradePrevention::CancelOldest;
+    let limit_order = match limit(1, OrderSide::Ask, 100, 5).with_owner(7) {
+        OrderRequest::Limit(limit_order) => limit_order,
+        _ => unreachable!(),
+    };
+    let stop_order = match OrderRequest::new_stop_loss(
+        2,
+        AssetPair::new("BASE", "QUOTE"),
+        OrderSide::Ask,
+        90,
+        5,
+        2,
+    ) {
+        OrderRequest::Stop(stop_order) => stop_order,
+        _ => unreachable!(),
+    };
+    engine.process(OrderRequest::new_oco(limit_order, stop_order));
+
+    let results = engine.process(limit(3, OrderSide::Bid, 100, 5).with_owner(7));
+
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::Cancelled {
+            id: 1,
+            target_id: 2,
+            ..
+        })
+    )));
+    assert!(engine.limit_order(OrderSide::Ask, 1).is_none());
+    assert!(engine
+        .stop_order_repository
+        .get_by_order_id(&mut (), &2)
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn expiry_of_an_oco_leg_cancels_the_linked_order() {
+    let mut engine = TestMatchingEngine::new();
+    let limit_order = match OrderRequest::new_good_till_date(
+        1,
+        AssetPair::new("BASE", "QUOTE"),
+        OrderSide::Ask,
+        100,
+        5,
+        50,
+        1,
+    ) {
+        OrderRequest::Limit(limit_order) => limit_order,
+        _ => unreachable!(),
+    };
+    let stop_order = match OrderRequest::new_stop_loss(
+        2,
+        AssetPair::new("BASE", "QUOTE"),
+        OrderSide::Ask,
+        90,
+        5,
+        2,
+    ) {
+        OrderRequest::Stop(stop_order) => stop_order,
+        _ => unreachable!(),
+    };
+    engine.process(OrderRequest::new_oco(limit_order, stop_order));
+
+    let results = engine.expire_orders(&mut (), 60).unwrap();
+
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::Cancelled {
+            id: 1,
+            target_id: 2,
+            ..
+        })
+    )));
+    assert!(engine.limit_order(OrderSide::Ask, 1).is_none());
+    assert!(engine
+        .stop_order_repository
+        .get_by_order_id(&mut (), &2)
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn pegged_orders_are_repriced_only_when_the_best_prices_change() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Bid, 100, 10));
+    engine.process(limit(2, OrderSide::Ask, 105, 10));
+    engine.process(OrderRequest::new_pegged(
+        3,
+        AssetPair::new("BASE", "QUOTE"),
+        OrderSide::Bid,
+        PegType::Primary,
+        None,
+        None,
+        10,
+        3,
+    ));
+    assert_eq!(engine.limit_order(OrderSide::Bid, 3).unwrap().price, 100);
+    let pegged_order_scans = engine.bid_limit_order_repository.pegged_order_scans.get();
+
+    let results = engine.process(limit(4, OrderSide::Bid, 99, 10));
+
+    assert!(!results
+        .iter()
+        .any(|result| matches!(result, Ok(MatchingEngineOutput::Repriced { .. }))));
+    assert_eq!(
+        engine.bid_limit_order_repository.pegged_order_scans.get(),
+        pegged_order_scans
+    );
+
+    let results = engine.process(limit(5, OrderSide::Bid, 101, 10));
+
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::Repriced {
+            id: 3,
+            price: 101,
+            ..
+        })
+    )));
+    assert_eq!(engine.limit_order(OrderSide::Bid, 3).unwrap().price, 101);
+}
+
+fn pegged(
+    id: u64,
+    side: OrderSide,
+    peg_type: PegType,
+    quantity: u64,
+) -> OrderRequest<u64, &'static str, u64, u64> {
+    OrderRequest::new_pegged(
+        id,
+        AssetPair::new("BASE", "QUOTE"),
+        side,
+        peg_type,
+        None,
+        None,
+        quantity,
+        id,
+    )
+}
+
+#[test]
+fn repriced_pegged_order_loses_its_time_priority() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Bid, 100, 10));
+    engine.process(pegged(2, OrderSide::Bid, PegType::Primary, 10));
+    engine.now_ms = 10;
+    engine.process(limit(3, OrderSide::Bid, 101, 10));
+    assert_eq!(engine.limit_order(OrderSide::Bid, 2).unwrap().price, 101);
+
+    let results = engine.process(limit(4, OrderSide::Ask, 101, 10));
+
+    assert!(results
+        .iter()
+        .any(|result| matches!(result, Ok(MatchingEngineOutput::Filled { id: 3, .. }))));
+    assert_eq!(engine.limit_order(OrderSide::Bid, 2).unwrap().quantity, 10);
+}
+
+#[test]
+fn midpoint_pegged_orders_are_rounded_to_the_tick_away_from_the_opposite_side() {
+    let mut engine = TestMatchingEngine::new();
+    engine.tick_size = 2;
+    engine.process(limit(1, OrderSide::Bid, 100, 10));
+    engine.process(limit(2, OrderSide::Ask, 106, 10));
+
+    engine.process(pegged(3, OrderSide::Ask, PegType::Midpoint, 10));
+    engine.process(pegged(4, OrderSide::Bid, PegType::Midpoint, 10));
+
+    assert_eq!(engine.limit_order(OrderSide::Ask, 3).unwrap().price, 104);
+    assert_eq!(engine.limit_order(OrderSide::Bid, 4).unwrap().price, 102);
+}
+
+fn protected_constrained_market(
+    id: u64,
+    quantity: u64,
+    execution_constraint: ExecutionConstraint<u64>,
+    offset: u64,
+) -> OrderRequest<u64, &'static str, u64, u64> {
+    match OrderRequest::new_constrained_market(
+        id,
+        AssetPair::new("BASE", "QUOTE"),
+        OrderSide::Bid,
+        MarketOrderQuantity::Base(quantity),
+        execution_constraint,
+        id,
+    ) {
+        OrderRequest::Market(market_order) => OrderRequest::Market(MarketOrder {
+            price_protection: Some(PriceProtection::Absolute(offset)),
+            ..market_order
+        }),
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn all_or_none_market_order_is_rejected_beyond_its_price_protection() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Ask, 100, 5));
+    engine.process(limit(2, OrderSide::Ask, 110, 5));
+
+    let results = engine.process(protected_constrained_market(
+        3,
+        10,
+        ExecutionConstraint::AllOrNone,
+        5,
+    ));
+
+    assert!(matches!(
+        results.as_slice(),
+        [Err(MatchingEngineFailure::ExecutionConstraintNotMet(3))]
+    ));
+    assert_eq!(engine.limit_order(OrderSide::Ask, 1).unwrap().quantity, 5);
+}
+
+#[test]
+fn minimum_quantity_market_order_counts_only_the_liquidity_within_its_price_protection() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Ask, 100, 5));
+    engine.process(limit(2, OrderSide::Ask, 110, 5));
+
+    let results = engine.process(protected_constrained_market(
+        3,
+        10,
+        ExecutionConstraint::MinQuantity(6),
+        5,
+    ));
+    assert!(matches!(
+        results.as_slice(),
+        [Err(MatchingEngineFailure::ExecutionConstraintNotMet(3))]
+    ));
+
+    let results = engine.process(protected_constrained_market(
+        4,
+        10,
+        ExecutionConstraint::MinQuantity(5),
+        5,
+    ));
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::PartiallyFilled {
+            id: 4,
+            price: 100,
+            quantity: 5,
+            ..
+        })
+    )));
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::RemainderCancelled { id: 4, .. })
+    )));
+    assert_eq!(engine.limit_order(OrderSide::Ask, 2).unwrap().quantity, 5);
+}
+
+#[test]
+fn all_or_none_limit_order_trades_only_when_the_crossing_liquidity_fills_it() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Ask, 100, 5));
+    engine.process(limit(2, OrderSide::Ask, 101, 5));
+    let all_or_none = |id, price| {
+        OrderRequest::new_constrained_limit(
+            id,
+            AssetPair::new("BASE", "QUOTE"),
+            OrderSide::Bid,
+            price,
+            10,
+            TimeInForce::GTC,
+            ExecutionConstraint::AllOrNone,
+            id,
+        )
+    };
+
+    let results = engine.process(all_or_none(3, 100));
+    assert!(matches!(
+        results.as_slice(),
+        [Err(MatchingEngineFailure::ExecutionConstraintNotMet(3))]
+    ));
+    assert!(engine.limit_order(OrderSide::Bid, 3).is_none());
+
+    let results = engine.process(all_or_none(4, 101));
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::Filled {
+            id: 4,
+            price: 101,
+            quantity: 5,
+            ..
+        })
+    )));
+    assert!(engine.limit_order(OrderSide::Ask, 2).is_none());
+}
+
+fn quote_market(id: u64, quote_quantity: u64) -> OrderRequest<u64, &'static str, u64, u64> {
+    OrderRequest::new_market_quote(
+        id,
+        AssetPair::new("BASE", "QUOTE"),
+        OrderSide::Bid,
+        quote_quantity,
+        id,
+    )
+}
+
+#[test]
+fn quote_market_order_is_sized_at_the_price_of_each_level() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Ask, 10, 5));
+    engine.process(limit(2, OrderSide::Ask, 20, 5));
+
+    let results = engine.process(quote_market(3, 150));
+
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::PartiallyFilled {
+            id: 3,
+            price: 10,
+            quantity: 5,
+            quote_quantity: 50,
+            ..
+        })
+    )));
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::Filled {
+            id: 3,
+            price: 20,
+            quantity: 5,
+            quote_quantity: 100,
+            ..
+        })
+    )));
+    assert!(!results
+        .iter()
+        .any(|result| matches!(result, Ok(MatchingEngineOutput::RemainderCancelled { .. }))));
+}
+
+#[test]
+fn unspent_quote_quantity_of_a_market_order_is_cancelled() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Ask, 30, 10));
+
+    let results = engine.process(quote_market(2, 100));
+
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::Filled {
+            id: 2,
+            quantity: 3,
+            quote_quantity: 90,
+            ..
+        })
+    )));
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::RemainderCancelled {
+            id: 2,
+            quantity: MarketOrderQuantity::Quote(10),
+            ..
+        })
+    )));
+    assert_eq!(engine.limit_order(OrderSide::Ask, 1).unwrap().quantity, 7);
+}
+
+#[test]
+fn quote_market_order_less_than_a_lot_is_cancelled() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Ask, 0, 10));
+    engine.process(limit(2, OrderSide::Ask, 30, 10));
+
+    let results = engine.process(quote_market(3, 20));
+
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::RemainderCancelled {
+            id: 3,
+            quantity: MarketOrderQuantity::Quote(20),
+            ..
+        })
+    )));
+    assert_eq!(engine.limit_order(OrderSide::Ask, 1).unwrap().quantity, 10);
+}
+
+#[test]
+fn top_order_pro_rata_allocates_to_the_lead_market_makers_assigned_by_the_venue() {
+    let mut engine = TestMatchingEngine::new();
+    engine.matching_policy = MatchingPolicy::TopOrderProRata {
+        lead_market_maker_basis_points: 5_000,
+        minimum_allocation: None,
+        lot_size: 1,
+    };
+    engine.lead_market_makers = vec![2];
+    engine.process(limit(1, OrderSide::Ask, 100, 10).with_owner(1));
+    engine.process(limit(2, OrderSide::Ask, 100, 10).with_owner(2));
+    // the role claimed by the order request is overridden by the venue
+    let claimed_role = match limit(3, OrderSide::Ask, 100, 10).with_owner(3) {
+        OrderRequest::Limit(limit_order) => OrderRequest::Limit(LimitOrder {
+            participant_role: ParticipantRole::LeadMarketMaker,
+            ..limit_order
+        }),
+        _ => unreachable!(),
+    };
+    engine.process(claimed_role);
+    assert!(engine.limit_order(OrderSide::Ask, 1).unwrap().top_order);
+    assert_eq!(
+        engine
+            .limit_order(OrderSide::Ask, 3)
+            .unwrap()
+            .participant_role,
+        ParticipantRole::Regular
+    );
+
+    engine.process(limit(4, OrderSide::Bid, 100, 20).with_owner(4));
+
+    // the top order is filled first, the lead market maker receives half of the rest,
+    // and the remainder is allocated pro-rata
+    assert!(engine.limit_order(OrderSide::Ask, 1).is_none());
+    assert_eq!(engine.limit_order(OrderSide::Ask, 2).unwrap().quantity, 3);
+    assert_eq!(engine.limit_order(OrderSide::Ask, 3).unwrap().quantity, 7);
+}
+
+/// the last indicative clearing published in `results`.
+fn indicative_auction(
+    results: &OrderProcessingResult<u64, u64, u64>,
+) -> Option<Option<AuctionClearing<u64, u64>>> {
+    results.iter().rev().find_map(|result| match result {
+        Ok(MatchingEngineOutput::IndicativeAuction { clearing, .. }) => Some(*clearing),
+        _ => None,
+    })
+}
+
+#[test]
+fn indicative_auction_is_published_on_every_change_of_the_collected_orders() {
+    let mut engine = TestMatchingEngine::new();
+    engine.trading_schedule.session_state = SessionState::PreOpen;
+
+    let results = engine.process(limit(1, OrderSide::Bid, 100, 10));
+    assert!(matches!(indicative_auction(&results), Some(None)));
+
+    let results = engine.process(limit(2, OrderSide::Ask, 99, 6));
+    let clearing = indicative_auction(&results).unwrap().unwrap();
+    assert_eq!(clearing.price, 99);
+    assert_eq!(clearing.matched_quantity, 6);
+    assert_eq!(clearing.imbalance, Some((OrderSide::Bid, 4)));
+
+    let results = engine.process(limit(3, OrderSide::Ask, 100, 6));
+    let clearing = indicative_auction(&results).unwrap().unwrap();
+    assert_eq!(clearing.price, 100);
+    assert_eq!(clearing.matched_quantity, 10);
+    assert_eq!(clearing.imbalance, Some((OrderSide::Ask, 2)));
+    assert!(!results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::Filled { .. } | MatchingEngineOutput::PartiallyFilled { .. })
+    )));
+    assert_eq!(engine.limit_order(OrderSide::Bid, 1).unwrap().quantity, 10);
+}
+
+#[test]
+fn uncross_cancels_the_newest_order_of_the_same_owner() {
+    let mut engine = TestMatchingEngine::new();
+    engine.trading_schedule.session_state = SessionState::PreOpen;
+    engine.process(limit(1, OrderSide::Bid, 100, 5).with_owner(1));
+    engine.process(limit(2, OrderSide::Ask, 100, 5).with_owner(1));
+    engine.process(limit(3, OrderSide::Ask, 100, 3).with_owner(2));
+
+    let results = engine.uncross(&mut ()).unwrap();
+
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::SelfTradePrevented {
+            id: 2,
+            opposite_id: 1,
+            quantity: Some(5),
+            opposite_quantity: None,
+            ..
+        })
+    )));
+    assert!(!results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::Filled { id: 2, .. }
+            | MatchingEngineOutput::PartiallyFilled { id: 2, .. })
+    )));
+    assert!(engine.limit_order(OrderSide::Ask, 2).is_none());
+    assert!(engine.limit_order(OrderSide::Ask, 3).is_none());
+    assert_eq!(engine.limit_order(OrderSide::Bid, 1).unwrap().quantity, 2);
+}
+
+#[test]
+fn uncross_decrements_both_orders_of_the_same_owner() {
+    let mut engine = TestMatchingEngine::new();
+    engine.self_trade_prevention = SelfTradePrevention::DecrementAndCancel;
+    engine.trading_schedule.session_state = SessionState::PreOpen;
+    engine.process(limit(1, OrderSide::Bid, 100, 5).with_owner(1));
+    engine.process(limit(2, OrderSide::Ask, 100, 3).with_owner(1));
+    engine.process(limit(3, OrderSide::Ask, 100, 5).with_owner(2));
+
+    let results = engine.uncross(&mut ()).unwrap();
+
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::SelfTradePrevented {
+            id: 2,
+            opposite_id: 1,
+            quantity: Some(3),
+            opposite_quantity: Some(3),
+            ..
+        })
+    )));
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::Filled {
+            id: 1,
+            quantity: 2,
+            ..
+        })
+    )));
+    assert!(engine.limit_order(OrderSide::Ask, 2).is_none());
+    assert_eq!(engine.limit_order(OrderSide::Ask, 3).unwrap().quantity, 3);
+}
+
+#[test]
+fn stop_orders_triggered_by_the_uncross_are_not_matched_continuously() {
+    let mut engine = TestMatchingEngine::new();
+    engine.trading_schedule.session_state = SessionState::PreOpen;
+    engine.process(limit(1, OrderSide::Bid, 100, 5));
+    engine.process(limit(2, OrderSide::Ask, 100, 5));
+    engine.process(limit(3, OrderSide::Bid, 98, 4));
+    engine.process(OrderRequest::new_stop_loss_limit(
+        4,
+        AssetPair::new("BASE", "QUOTE"),
+        OrderSide::Ask,
+        100,
+        99,
+        4,
+        4,
+    ));
+    engine.process(OrderRequest::new_stop_loss(
+        5,
+        AssetPair::new("BASE", "QUOTE"),
+        OrderSide::Ask,
+        100,
+        4,
+        5,
+    ));
+
+    let results = engine.uncross(&mut ()).unwrap();
+
+    // the stop-limit order is collected at 99 without trading with the bid order at 98
+    assert!(results
+        .iter()
+        .any(|result| matches!(result, Ok(MatchingEngineOutput::Triggered { id: 4, .. }))));
+    assert!(!results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::Filled { id: 4, .. }
+            | MatchingEngineOutput::PartiallyFilled { id: 4, .. })
+    )));
+    assert_eq!(engine.limit_order(OrderSide::Ask, 4).unwrap().quantity, 4);
+    assert_eq!(engine.limit_order(OrderSide::Bid, 3).unwrap().quantity, 4);
+    // market orders are not allowed in the call phase
+    assert!(results
+        .iter()
+        .any(|result| matches!(result, Err(MatchingEngineFailure::NotAllowedInSession(5)))));
+}
+
+#[test]
+fn stop_orders_triggered_by_the_uncross_join_the_next_uncross() {
+    let mut engine = TestMatchingEngine::new();
+    engine.trading_schedule.session_state = SessionState::PreOpen;
+    engine.process(limit(1, OrderSide::Bid, 100, 5));
+    engine.process(limit(2, OrderSide::Ask, 100, 5));
+    engine.process(limit(3, OrderSide::Bid, 99, 4));
+    engine.process(OrderRequest::new_stop_loss_limit(
+        4,
+        AssetPair::new("BASE", "QUOTE"),
+        OrderSide::Ask,
+        100,
+        98,
+        4,
+        4,
+    ));
+
+    let results = engine.uncross(&mut ()).unwrap();
+
+    // the collected stop-limit order crosses the bid order at 99 and is executed at the
+    // clearing price of the book it crosses
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Ok(MatchingEngineOutput::Filled {
+            id: 4,
+            price: 98,
+            quantity: 4,
+            ..
+        })
+    )));
+    assert!(engine.limit_order(OrderSide::Bid, 3).is_none());
+}
+
+fn day(
+    id: u64,
+    side: OrderSide,
+    price: u64,
+    quantity: u64,
+) -> OrderRequest<u64, &'static str, u64, u64> {
+    OrderRequest::new_limit(
+        id,
+        AssetPair::new("BASE", "QUOTE"),
+        side,
+        price,
+        quantity,
+        TimeInForce::DAY,
+        id,
+    )
+}
+
+#[test]
+fn day_order_expires_at_the_session_close() {
+    let mut engine = TestMatchingEngine::new();
+    engine.trading_schedule.session_close_time_ms = Some(100);
+    engine.process(day(1, OrderSide::Bid, 100, 10));
+    engine.process(limit(2, OrderSide::Bid, 99, 10));
+    assert_eq!(
+        engine
+            .limit_order(OrderSide::Bid, 1)
+            .unwrap()
+            .expire_time_ms,
+        Some(100)
+    );
+
+    engine.now_ms = 100;
+    let results = engine.expire_orders(&mut (), 100).unwrap();
+
+    assert!(matches!(
+        results.as_slice(),
+        [Ok(MatchingEngineOutput::Expired { id: 1, .. })]
+    ));
+    assert!(engine.limit_order(OrderSide::Bid, 1).is_none());
+    assert!(engine.limit_order(OrderSide::Bid, 2).is_some());
+}
+
+#[test]
+fn day_order_is_expired_on_the_transition_to_closed() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(day(1, OrderSide::Bid, 100, 10));
+    engine.process(limit(2, OrderSide::Bid, 99, 10));
+
+    engine.trading_schedule.session_state = SessionState::Closed;
+
+    // the DAY order no longer serves as a reference price before its removal
+    assert_eq!(engine.peg_reference(&mut ()).unwrap().best_bid, Some(99));
+    let book_orders = engine.book_orders(&mut (), OrderSide::Bid).unwrap();
+    assert_eq!(
+        book_orders.iter().map(|order| order.id).collect::<Vec<_>>(),
+        vec![2]
+    );
+    let results = engine.expire_orders(&mut (), 0).unwrap();
+    assert!(matches!(
+        results.as_slice(),
+        [Ok(MatchingEngineOutput::Expired { id: 1, .. })]
+    ));
+    assert!(engine.limit_order(OrderSide::Bid, 1).is_none());
+    assert!(engine.limit_order(OrderSide::Bid, 2).is_some());
+}
+
+#[test]
+fn closed_session_accepts_only_cancels() {
+    let mut engine = TestMatchingEngine::new();
+    engine.process(limit(1, OrderSide::Bid, 100, 10));
+    engine.trading_schedule.session_state = SessionState::Closed;
+
+    let results = engine.process(limit(2, OrderSide::Ask, 100, 10));
+    assert!(matches!(
+        results.as_slice(),
+        [Err(MatchingEngineFailure::NotAllowedInSession(2))]
+    ));
+
+    let results = engine.process(OrderRequest::new_cancel(
+        3,
+        AssetPair::new("BASE", "QUOTE"),
+        1,
+        OrderType::Limit,
+        OrderSide::Bid,
+    ));
+    assert!(matches!(
+        results.as_slice(),
+        [Ok(MatchingEngineOutput::Cancelled {
+            id: 3,
+            target_id: 1,
+            ..
+        })]
+    ));
+}
+
+#[test]
+fn pre_open_session_rejects_market_orders_and_collects_limit_orders() {
+    let mut engine = TestMatchingEngine::new();
+    engine.trading_schedule.session_state = SessionState::PreOpen;
+    engine.process(limit(1, OrderSide::Ask, 100, 10));
+
+    let results = engine.process(OrderRequest::new_market(
+        2,
+        AssetPair::new("BASE", "QUOTE"),
+        OrderSide::Bid,
+        5,
+        2,
+    ));
+    assert!(matches!(
+        results.as_slice(),
+        [Err(MatchingEngineFailure::NotAllowedInSession(2))]
+    ));
+
+    let results = engine.process(fill_or_kill(3, OrderSide::Bid, 100, 5));
+    assert!(matches!(
+        results.as_slice(),
+        [Err(MatchingEngineFailure::NotAllowedInSession(3))]
+    ));
+
+    engine.process(limit(4, OrderSide::Bid, 100, 5));
+    assert_eq!(engine.limit_order(OrderSide::Ask, 1).unwrap().quantity, 10);
+    assert_eq!(engine.limit_order(OrderSide::Bid, 4).unwrap().quantity, 5);
+}
@@ -0,0 +1,36 @@
+use super::Quantity;
+
+/// a quantity which can be split in proportion for pro-rata matching.
+pub trait Allocation: Quantity {
+    /// share of the quantity in proportion of `weight` to `total_weight`, rounded down to a
+    /// multiple of `lot_size`. `None` if the share is less than a lot.
+    fn allocation(&self, weight: Self, total_weight: Self, lot_size: Self) -> Option<Self>;
+
+    /// share of the quantity in basis points, rounded down to a multiple of `lot_size`.
+    /// `None` if the share is less than a lot.
+    fn fraction(&self, basis_points: u64, lot_size: Self) -> Option<Self>;
+}
+
+macro_rules! impl_allocation_for_unsigned {
+    ($($t:ty),*) => {
+        $(
+            impl Allocation for $t {
+                fn allocation(&self, weight: Self, total_weight: Self, lot_size: Self) -> Option<Self> {
+                    let share = *self as u128 * weight as u128 / total_weight as u128;
+                    let allocation = (share / lot_size as u128 * lot_size as u128) as Self;
+                    if allocation == 0 {
+                        None
+                    } else {
+                        Some(allocation)
+                    }
+                }
+
+                fn fraction(&self, basis_points: u64, lot_size: Self) -> Option<Self> {
+                    self.allocation(basis_points as Self, 10_000, lot_size)
+                }
+            }
+        )*
+    };
+}
+
+impl_allocation_for_unsigned!(u32, u64, usize);
@@ -0,0 +1,13 @@
+use super::{OrderSide, Price, Quantity};
+
+/// result of an uncross of the orders collected in a call auction.
+#[derive(Debug, Copy, Clone)]
+pub struct AuctionClearing<P: Price, Q: Quantity> {
+    /// single price at which all crossing orders are executed
+    pub price: P,
+    /// quantity executed on each side at the price
+    pub matched_quantity: Q,
+    /// side and quantity left unmatched among the crossing orders. `None` if both sides
+    /// are matched in full.
+    pub imbalance: Option<(OrderSide, Q)>,
+}
@@ -0,0 +1,29 @@
+use super::{Asset, LimitOrder, OcoOrder, OrderId, Price, Quantity, StopOrder};
+
+/// take-profit and stop-loss orders attached to the entry order of a bracket. they are
+/// submitted as a one-cancels-other pair once the entry order is filled, and sized to its
+/// cumulative filled quantity.
+#[derive(Debug, Clone)]
+pub struct ChildOrders<ID: OrderId, A: Asset, P: Price, Q: Quantity> {
+    pub parent_id: ID,
+    pub take_profit_order: LimitOrder<ID, A, P, Q>,
+    pub stop_loss_order: StopOrder<ID, A, P, Q>,
+    /// cumulative filled quantity of the parent order, `None` until the children are submitted.
+    pub filled_quantity: Option<Q>,
+}
+
+impl<ID: OrderId, A: Asset, P: Price, Q: Quantity> ChildOrders<ID, A, P, Q> {
+    pub fn to_oco_order(&self, quantity: Q) -> OcoOrder<ID, A, P, Q> {
+        OcoOrder {
+            limit_order: LimitOrder {
+                quantity,
+                original_quantity: quantity,
+                ..self.take_profit_order.clone()
+            },
+            stop_order: StopOrder {
+                quantity,
+                ..self.stop_loss_order.clone()
+            },
+        }
+    }
+}
@@ -0,0 +1,20 @@
+use super::Quantity;
+
+/// quantity an incoming order has to trade in a single matching pass,
+/// or it is rejected without trading at all.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum ExecutionConstraint<Q: Quantity> {
+    AllOrNone,
+    /// minimum quantity in the unit of the order quantity
+    /// (the quote asset for market orders sized in it)
+    MinQuantity(Q),
+}
+
+impl<Q: Quantity> ExecutionConstraint<Q> {
+    pub fn required_quantity(&self, quantity: Q) -> Q {
+        match *self {
+            ExecutionConstraint::AllOrNone => quantity,
+            ExecutionConstraint::MinQuantity(min_quantity) => min_quantity.min(quantity),
+        }
+    }
+}
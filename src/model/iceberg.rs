@@ -0,0 +1,30 @@
+use super::Quantity;
+
+/// an iceberg order displays only a peak of its quantity in the order book.
+/// the `quantity` of a resting iceberg order is the displayed peak and the rest is kept
+/// as a hidden reserve which replenishes the peak whenever it is fully consumed.
+#[derive(Debug, Copy, Clone)]
+pub struct Iceberg<Q: Quantity> {
+    /// size of each displayed peak
+    pub peak_quantity: Q,
+    /// reserve not displayed in the order book. `None` if nothing is left.
+    pub hidden_quantity: Option<Q>,
+}
+
+impl<Q: Quantity> Iceberg<Q> {
+    pub fn new(peak_quantity: Q) -> Self {
+        Self {
+            peak_quantity,
+            hidden_quantity: None,
+        }
+    }
+
+    /// splits the given quantity into the displayed peak and the hidden reserve.
+    pub fn split(&self, quantity: Q) -> (Q, Option<Q>) {
+        if quantity > self.peak_quantity {
+            (self.peak_quantity, Some(quantity - self.peak_quantity))
+        } else {
+            (quantity, None)
+        }
+    }
+}
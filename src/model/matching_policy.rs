@@ -0,0 +1,25 @@
+use super::Quantity;
+
+/// how the incoming quantity is allocated across the resting orders at the best price.
+#[derive(Debug, Copy, Clone)]
+pub enum MatchingPolicy<Q: Quantity> {
+    /// price-time priority: the orders are filled one at a time in the order returned by
+    /// `LimitOrderRepositoryLike::next`.
+    Fifo,
+    /// the orders of the price level are filled at once in proportion to their quantities.
+    /// each allocation is rounded down to a multiple of `lot_size`, which must be positive, and
+    /// dropped if less than `minimum_allocation`. the quantity left by the rounding is allocated
+    /// in time priority.
+    ProRata {
+        minimum_allocation: Option<Q>,
+        lot_size: Q,
+    },
+    /// pro-rata with priority allocations: the top order (see `LimitOrder::top_order`) is filled
+    /// first, then the lead market makers receive `lead_market_maker_basis_points` of the rest
+    /// in proportion to their quantities, and the remainder is allocated like `ProRata`.
+    TopOrderProRata {
+        lead_market_maker_basis_points: u64,
+        minimum_allocation: Option<Q>,
+        lot_size: Q,
+    },
+}
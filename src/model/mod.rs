@@ -1,19 +1,51 @@
+mod allocation;
 mod asset;
 mod asset_pair;
+mod auction_clearing;
+mod child_orders;
+mod execution_constraint;
+mod iceberg;
+mod matching_policy;
+mod notional;
 mod order;
 mod order_id;
+mod order_link;
 mod order_request;
 mod order_side;
 mod order_type;
+mod participant_role;
+mod peg;
+mod post_only;
 mod price;
+mod price_protection;
 mod quantity;
+mod self_trade_prevention;
+mod time_in_force;
+mod trading_session;
+mod trailing_stop;
 
+pub use allocation::*;
 pub use asset::*;
 pub use asset_pair::*;
+pub use auction_clearing::*;
+pub use child_orders::*;
+pub use execution_constraint::*;
+pub use iceberg::*;
+pub use matching_policy::*;
+pub use notional::*;
 pub use order::*;
 pub use order_id::*;
+pub use order_link::*;
 pub use order_request::*;
 pub use order_side::*;
 pub use order_type::*;
+pub use participant_role::*;
+pub use peg::*;
+pub use post_only::*;
 pub use price::*;
+pub use price_protection::*;
 pub use quantity::*;
+pub use self_trade_prevention::*;
+pub use time_in_force::*;
+pub use trading_session::*;
+pub use trailing_stop::*;
@@ -0,0 +1,34 @@
+use super::{Price, Quantity};
+
+/// conversion between quantities of the base asset and the quote asset at a price.
+pub trait Notional<P: Price>: Quantity {
+    /// quote quantity of this base quantity at the given price.
+    fn notional(&self, price: P) -> Self;
+
+    /// base quantity whose notional at the given price fits in the quote quantity,
+    /// rounded down to the base lot. `None` if it is less than a lot, or at a zero price.
+    fn from_notional(quote_quantity: Self, price: P) -> Option<Self>;
+}
+
+macro_rules! impl_notional_for_unsigned {
+    ($($t:ty),*) => {
+        $(
+            impl Notional<u64> for $t {
+                /// saturates at the largest quantity instead of wrapping around.
+                fn notional(&self, price: u64) -> Self {
+                    Self::try_from(*self as u128 * price as u128).unwrap_or(Self::MAX)
+                }
+
+                fn from_notional(quote_quantity: Self, price: u64) -> Option<Self> {
+                    let quantity = (quote_quantity as u128).checked_div(price as u128)?;
+                    match Self::try_from(quantity) {
+                        Ok(0) | Err(_) => None,
+                        Ok(quantity) => Some(quantity),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_notional_for_unsigned!(u32, u64, usize);
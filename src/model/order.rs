@@ -1,47 +1,444 @@
-use super::{Asset, AssetPair, OrderId, OrderSide, OrderType, Price, Quantity};
+use super::{
+    Asset, AssetPair, ChildOrders, ExecutionConstraint, Iceberg, OrderId, OrderLink, OrderSide,
+    OrderType, ParticipantRole, Peg, PostOnly, Price, PriceProtection, Quantity, TimeInForce,
+    TrailingStop,
+};
 
-#[derive(Debug)]
-pub struct MarketOrder<ID: OrderId, A: Asset, Q: Quantity> {
+#[derive(Debug, Copy, Clone)]
+pub enum MarketOrderQuantity<Q: Quantity> {
+    /// quantity of the base asset to buy or sell
+    Base(Q),
+    /// quantity of the quote asset to spend (buy) or to receive (sell)
+    Quote(Q),
+}
+
+#[derive(Debug, Clone)]
+pub struct MarketOrder<ID: OrderId, A: Asset, P: Price, Q: Quantity> {
     pub id: ID,
     pub asset_pair: AssetPair<A>,
     pub side: OrderSide,
-    pub quantity: Q,
+    /// account the order belongs to. orders of the same owner do not trade with each other
+    /// (see `SelfTradePrevention`). `None` opts out of self-trade prevention.
+    pub owner: Option<u64>,
+    pub quantity: MarketOrderQuantity<Q>,
+    /// `Some` for market orders with slippage protection.
+    pub price_protection: Option<PriceProtection<P>>,
+    /// `Some` for all-or-none and minimum quantity orders.
+    pub execution_constraint: Option<ExecutionConstraint<Q>>,
+    /// `true` for market-to-limit orders. they trade only against the best level and the
+    /// unfilled remainder rests as a limit order at the price of the first fill.
+    pub market_to_limit: bool,
     pub timestamp_ms: u64,
 }
 
-impl<ID: OrderId, A: Asset, Q: Quantity> MarketOrder<ID, A, Q> {
-    pub fn sub_quantity(&self, sub: Q) -> Self {
+impl<ID: OrderId, A: Asset, P: Price, Q: Quantity> MarketOrder<ID, A, P, Q> {
+    pub fn order_type(&self) -> OrderType {
+        if self.market_to_limit {
+            OrderType::MarketToLimit
+        } else {
+            OrderType::Market
+        }
+    }
+
+    /// subtracts the traded base quantity and its quote quantity from the order.
+    pub fn sub_quantity(&self, sub: Q, sub_quote: Q) -> Self {
+        let quantity = match self.quantity {
+            MarketOrderQuantity::Base(quantity) => MarketOrderQuantity::Base(quantity - sub),
+            MarketOrderQuantity::Quote(quantity) => {
+                MarketOrderQuantity::Quote(quantity - sub_quote)
+            }
+        };
         Self {
-            id: self.id,
-            asset_pair: self.asset_pair.clone(),
-            side: self.side,
-            quantity: self.quantity - sub,
-            timestamp_ms: self.timestamp_ms,
+            quantity,
+            ..self.clone()
+        }
+    }
+
+    /// returns the limit order a market-to-limit order turns into at the price of its first fill.
+    pub fn to_limit_order(&self, price: P, quantity: Q) -> LimitOrder<ID, A, P, Q> {
+        LimitOrder {
+            owner: self.owner,
+            market_to_limit: self.market_to_limit,
+            ..LimitOrder::new(
+                self.id,
+                self.asset_pair.clone(),
+                self.side,
+                price,
+                quantity,
+                self.timestamp_ms,
+            )
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct LimitOrder<ID: OrderId, A: Asset, P: Price, Q: Quantity> {
     pub id: ID,
     pub asset_pair: AssetPair<A>,
     pub side: OrderSide,
+    /// account the order belongs to. orders of the same owner do not trade with each other
+    /// (see `SelfTradePrevention`). `None` opts out of self-trade prevention.
+    pub owner: Option<u64>,
     pub price: P,
     pub quantity: Q,
+    /// size of the order when it was entered, or the new size set by its last amend.
+    pub original_quantity: Q,
+    /// cumulative filled quantity of the order, `None` until it has traded.
+    pub filled_quantity: Option<Q>,
+    pub time_in_force: TimeInForce,
+    /// `true` for a market-to-limit order turned into a limit order at the price of its first fill.
+    pub market_to_limit: bool,
+    /// `Some` for post-only (LimitMaker) orders.
+    pub post_only: Option<PostOnly>,
+    /// `Some` for iceberg orders.
+    pub iceberg: Option<Iceberg<Q>>,
+    /// `true` for hidden orders, which trade but are left out of market data and rank
+    /// behind displayed orders at the same price.
+    pub hidden: bool,
+    /// role of the owner at the venue, set by the engine when the order rests in the book
+    /// (see `MatchingEngine::participant_role`).
+    pub participant_role: ParticipantRole,
+    /// `true` for the order which established a new best price, set by the engine under
+    /// `MatchingPolicy::TopOrderProRata`.
+    pub top_order: bool,
+    /// `Some` for good-till-date orders.
+    pub expire_time_ms: Option<u64>,
+    /// `Some` for pegged orders. `price` is the current price of the order.
+    pub peg: Option<Peg<P>>,
+    /// `Some` for all-or-none and minimum quantity orders.
+    pub execution_constraint: Option<ExecutionConstraint<Q>>,
     pub timestamp_ms: u64,
 }
 
 impl<ID: OrderId, A: Asset, P: Price, Q: Quantity> LimitOrder<ID, A, P, Q> {
+    /// a good-till-cancelled limit order without any of the optional attributes, which the
+    /// other kinds of limit orders are built on.
+    pub fn new(
+        id: ID,
+        asset_pair: AssetPair<A>,
+        side: OrderSide,
+        price: P,
+        quantity: Q,
+        timestamp_ms: u64,
+    ) -> Self {
+        Self {
+            id,
+            asset_pair,
+            side,
+            owner: None,
+            price,
+            quantity,
+            original_quantity: quantity,
+            filled_quantity: None,
+            time_in_force: TimeInForce::GTC,
+            market_to_limit: false,
+            post_only: None,
+            iceberg: None,
+            hidden: false,
+            participant_role: ParticipantRole::Regular,
+            top_order: false,
+            expire_time_ms: None,
+            peg: None,
+            execution_constraint: None,
+            timestamp_ms,
+        }
+    }
+
+    pub fn order_type(&self) -> OrderType {
+        if self.market_to_limit {
+            OrderType::MarketToLimit
+        } else if self.post_only.is_some() {
+            OrderType::LimitMaker
+        } else {
+            OrderType::Limit
+        }
+    }
+
     pub fn sub_quantity(&self, sub: Q) -> Self {
         Self {
+            quantity: self.quantity - sub,
+            ..self.clone()
+        }
+    }
+
+    /// subtracts the traded quantity from the order and adds it to the filled quantity.
+    pub fn fill(&self, quantity: Q) -> Self {
+        self.sub_quantity(quantity).add_filled_quantity(quantity)
+    }
+
+    /// adds the traded quantity to the cumulative filled quantity of the order.
+    pub fn add_filled_quantity(&self, quantity: Q) -> Self {
+        let filled_quantity = match self.filled_quantity {
+            Some(filled_quantity) => filled_quantity + quantity,
+            None => quantity,
+        };
+        Self {
+            filled_quantity: Some(filled_quantity),
+            ..self.clone()
+        }
+    }
+
+    /// returns the order reduced to the remaining quantity `quantity` in place, keeping its
+    /// displayed peak as far as possible.
+    pub fn reduced(&self, quantity: Q) -> Self {
+        let order = Self {
+            original_quantity: self.total_quantity(quantity),
+            ..self.clone()
+        };
+        match order.iceberg {
+            Some(iceberg) if quantity > order.quantity => Self {
+                iceberg: Some(Iceberg {
+                    hidden_quantity: Some(quantity - order.quantity),
+                    ..iceberg
+                }),
+                ..order
+            },
+            Some(iceberg) => Self {
+                quantity,
+                iceberg: Some(Iceberg {
+                    hidden_quantity: None,
+                    ..iceberg
+                }),
+                ..order
+            },
+            None => Self { quantity, ..order },
+        }
+    }
+
+    /// the size of the order with the remaining quantity `quantity`, including its filled quantity.
+    fn total_quantity(&self, quantity: Q) -> Q {
+        match self.filled_quantity {
+            Some(filled_quantity) => quantity + filled_quantity,
+            None => quantity,
+        }
+    }
+
+    /// displayed quantity and hidden reserve of the order.
+    pub fn remaining_quantity(&self) -> Q {
+        match self.iceberg.and_then(|iceberg| iceberg.hidden_quantity) {
+            Some(hidden_quantity) => self.quantity + hidden_quantity,
+            None => self.quantity,
+        }
+    }
+
+    pub fn is_expired(&self, now_ms: u64) -> bool {
+        match self.expire_time_ms {
+            Some(expire_time_ms) => expire_time_ms <= now_ms,
+            None => false,
+        }
+    }
+
+    /// returns the order expiring at `expire_time_ms` at the latest.
+    pub fn expiring_at(&self, expire_time_ms: Option<u64>) -> Self {
+        let expire_time_ms = match (self.expire_time_ms, expire_time_ms) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        Self {
+            expire_time_ms,
+            ..self.clone()
+        }
+    }
+
+    /// `true` if the order can be collected in the order book without matching.
+    pub fn is_queueable(&self) -> bool {
+        matches!(self.time_in_force, TimeInForce::GTC | TimeInForce::DAY)
+            && self.peg.is_none()
+            && self.execution_constraint.is_none()
+    }
+
+    /// returns the order replaced by an amend with the remaining quantity `quantity`.
+    /// an iceberg order is split into peaks again when the order rests.
+    pub fn amended(&self, price: P, quantity: Q, timestamp_ms: u64) -> Self {
+        Self {
+            price,
+            quantity,
+            original_quantity: self.total_quantity(quantity),
+            iceberg: self.iceberg.map(|iceberg| Iceberg {
+                hidden_quantity: None,
+                ..iceberg
+            }),
+            timestamp_ms,
+            ..self.clone()
+        }
+    }
+
+    pub fn with_price(&self, price: P) -> Self {
+        Self {
+            price,
+            ..self.clone()
+        }
+    }
+
+    /// returns the order to be rested in the order book, displaying only the first peak
+    /// of an iceberg order.
+    pub fn displayed(&self) -> Self {
+        match self.iceberg {
+            Some(iceberg) if iceberg.hidden_quantity.is_none() => {
+                let (quantity, hidden_quantity) = iceberg.split(self.quantity);
+                Self {
+                    quantity,
+                    iceberg: Some(Iceberg {
+                        hidden_quantity,
+                        ..iceberg
+                    }),
+                    ..self.clone()
+                }
+            }
+            _ => self.clone(),
+        }
+    }
+
+    /// returns the next peak of an iceberg order whose displayed quantity has been consumed.
+    /// the new peak loses its time priority.
+    pub fn replenished(&self, timestamp_ms: u64) -> Option<Self> {
+        let iceberg = self.iceberg?;
+        let (quantity, hidden_quantity) = iceberg.split(iceberg.hidden_quantity?);
+        Some(Self {
+            quantity,
+            iceberg: Some(Iceberg {
+                hidden_quantity,
+                ..iceberg
+            }),
+            top_order: false,
+            timestamp_ms,
+            ..self.clone()
+        })
+    }
+}
+
+/// an order waiting in the trigger book until the last trade price reaches its stop price.
+#[derive(Debug, Clone)]
+pub struct StopOrder<ID: OrderId, A: Asset, P: Price, Q: Quantity> {
+    pub id: ID,
+    pub asset_pair: AssetPair<A>,
+    /// `StopLoss`, `StopLossLimit`, `TakeProfit`, `TakeProfitLimit` or `TrailingStop`
+    pub order_type: OrderType,
+    pub side: OrderSide,
+    /// account the order belongs to. orders of the same owner do not trade with each other
+    /// (see `SelfTradePrevention`). `None` opts out of self-trade prevention.
+    pub owner: Option<u64>,
+    pub stop_price: P,
+    /// limit price of the order placed when triggered. `None` places a market order.
+    pub price: Option<P>,
+    pub quantity: Q,
+    /// `Some` for trailing stop orders.
+    pub trailing_stop: Option<TrailingStop<P>>,
+    pub timestamp_ms: u64,
+}
+
+impl<ID: OrderId, A: Asset, P: Price, Q: Quantity> StopOrder<ID, A, P, Q> {
+    /// `true` if the order is triggered when the last trade price rises to the stop price,
+    /// `false` if it is triggered when the price falls to the stop price.
+    pub fn triggers_on_rise(&self) -> bool {
+        match (self.order_type, self.side) {
+            (OrderType::TakeProfit | OrderType::TakeProfitLimit, OrderSide::Bid) => false,
+            (OrderType::TakeProfit | OrderType::TakeProfitLimit, OrderSide::Ask) => true,
+            (_, OrderSide::Bid) => true,
+            (_, OrderSide::Ask) => false,
+        }
+    }
+
+    pub fn is_triggered_by(&self, last_price: P) -> bool {
+        if self.triggers_on_rise() {
+            last_price >= self.stop_price
+        } else {
+            last_price <= self.stop_price
+        }
+    }
+
+    /// moves the stop price of a trailing stop order after trades between `low` and `high`.
+    /// the stop price only moves in the direction protecting the position.
+    /// returns `true` if the stop price was moved.
+    pub fn trail(&mut self, low: P, high: P) -> bool {
+        if let Some(trailing_stop) = self.trailing_stop.as_mut() {
+            let stop_price = trailing_stop.trail(self.side, low, high);
+            let moved = match self.side {
+                OrderSide::Ask => stop_price > self.stop_price,
+                OrderSide::Bid => stop_price < self.stop_price,
+            };
+            if moved {
+                self.stop_price = stop_price;
+            }
+            moved
+        } else {
+            false
+        }
+    }
+
+    pub fn to_market_order(&self) -> MarketOrder<ID, A, P, Q> {
+        MarketOrder {
             id: self.id,
             asset_pair: self.asset_pair.clone(),
             side: self.side,
-            price: self.price,
-            quantity: self.quantity - sub,
+            owner: self.owner,
+            quantity: MarketOrderQuantity::Base(self.quantity),
+            price_protection: None,
+            execution_constraint: None,
+            market_to_limit: false,
             timestamp_ms: self.timestamp_ms,
         }
     }
+
+    pub fn to_limit_order(&self, price: P) -> LimitOrder<ID, A, P, Q> {
+        LimitOrder {
+            owner: self.owner,
+            ..LimitOrder::new(
+                self.id,
+                self.asset_pair.clone(),
+                self.side,
+                price,
+                self.quantity,
+                self.timestamp_ms,
+            )
+        }
+    }
+}
+
+/// a limit order and a stop order linked so that a fill or a cancel of one cancels the other.
+#[derive(Debug, Clone)]
+pub struct OcoOrder<ID: OrderId, A: Asset, P: Price, Q: Quantity> {
+    pub limit_order: LimitOrder<ID, A, P, Q>,
+    pub stop_order: StopOrder<ID, A, P, Q>,
+}
+
+impl<ID: OrderId, A: Asset, P: Price, Q: Quantity> OcoOrder<ID, A, P, Q> {
+    /// links of the limit order and the stop order to each other.
+    pub fn links(&self) -> (OrderLink<ID>, OrderLink<ID>) {
+        (
+            OrderLink {
+                order_id: self.limit_order.id,
+                linked_order_id: self.stop_order.id,
+                linked_order_type: self.stop_order.order_type,
+                linked_side: self.stop_order.side,
+            },
+            OrderLink {
+                order_id: self.stop_order.id,
+                linked_order_id: self.limit_order.id,
+                linked_order_type: self.limit_order.order_type(),
+                linked_side: self.limit_order.side,
+            },
+        )
+    }
+}
+
+/// an entry limit order with take-profit and stop-loss orders submitted once it is filled.
+#[derive(Debug, Clone)]
+pub struct BracketOrder<ID: OrderId, A: Asset, P: Price, Q: Quantity> {
+    pub entry_order: LimitOrder<ID, A, P, Q>,
+    pub take_profit_order: LimitOrder<ID, A, P, Q>,
+    pub stop_loss_order: StopOrder<ID, A, P, Q>,
+}
+
+impl<ID: OrderId, A: Asset, P: Price, Q: Quantity> BracketOrder<ID, A, P, Q> {
+    pub fn child_orders(&self) -> ChildOrders<ID, A, P, Q> {
+        ChildOrders {
+            parent_id: self.entry_order.id,
+            take_profit_order: self.take_profit_order.clone(),
+            stop_loss_order: self.stop_loss_order.clone(),
+            filled_quantity: None,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -52,10 +449,26 @@ pub struct AmendOrder<ID: OrderId, A: Asset, P: Price, Q: Quantity> {
     pub target_order_type: OrderType,
     pub side: OrderSide,
     pub price: P,
+    /// new stop price of a stop loss / take profit order. `None` keeps the current one.
+    pub stop_price: Option<P>,
+    /// new total size of the order, including its filled quantity.
     pub quantity: Q,
     pub timestamp_ms: u64,
 }
 
+impl<ID: OrderId, A: Asset, P: Price, Q: Quantity> AmendOrder<ID, A, P, Q> {
+    /// returns the cancel of the target order, for an amend which leaves nothing to trade.
+    pub fn cancel_order(&self) -> CancelOrder<ID, A> {
+        CancelOrder {
+            id: self.id,
+            asset_pair: self.asset_pair.clone(),
+            target_id: self.target_id,
+            target_order_type: self.target_order_type,
+            side: self.side,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct CancelOrder<ID: OrderId, A: Asset> {
     pub id: ID,
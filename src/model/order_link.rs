@@ -0,0 +1,11 @@
+use super::{OrderId, OrderSide, OrderType};
+
+/// link from an order to the order that is cancelled when it is filled or cancelled
+/// (one-cancels-other).
+#[derive(Debug, Clone)]
+pub struct OrderLink<ID: OrderId> {
+    pub order_id: ID,
+    pub linked_order_id: ID,
+    pub linked_order_type: OrderType,
+    pub linked_side: OrderSide,
+}
@@ -1,15 +1,100 @@
-use super::order::{AmendOrder, CancelOrder, LimitOrder, MarketOrder};
-use super::{Asset, AssetPair, OrderId, OrderSide, OrderType, Price, Quantity};
+use super::order::{
+    AmendOrder, BracketOrder, CancelOrder, LimitOrder, MarketOrder, MarketOrderQuantity, OcoOrder,
+    StopOrder,
+};
+use super::{
+    Asset, AssetPair, ExecutionConstraint, Iceberg, OrderId, OrderSide, OrderType, Peg, PegType,
+    PostOnly, Price, PriceProtection, Quantity, SessionState, TimeInForce, TrailingOffset,
+    TrailingStop,
+};
 
 #[derive(Debug)]
 pub enum OrderRequest<ID: OrderId, A: Asset, P: Price, Q: Quantity> {
-    Market(MarketOrder<ID, A, Q>),
+    Market(MarketOrder<ID, A, P, Q>),
     Limit(LimitOrder<ID, A, P, Q>),
+    Stop(StopOrder<ID, A, P, Q>),
+    Oco(OcoOrder<ID, A, P, Q>),
+    Bracket(BracketOrder<ID, A, P, Q>),
     Amend(AmendOrder<ID, A, P, Q>),
     Cancel(CancelOrder<ID, A>),
 }
 
 impl<ID: OrderId, A: Asset, P: Price, Q: Quantity> OrderRequest<ID, A, P, Q> {
+    pub fn id(&self) -> ID {
+        match self {
+            Self::Market(market_order) => market_order.id,
+            Self::Limit(limit_order) => limit_order.id,
+            Self::Stop(stop_order) => stop_order.id,
+            Self::Oco(oco_order) => oco_order.limit_order.id,
+            Self::Bracket(bracket_order) => bracket_order.entry_order.id,
+            Self::Amend(amend_order) => amend_order.id,
+            Self::Cancel(cancel_order) => cancel_order.id,
+        }
+    }
+
+    /// returns the request with the orders it places belonging to `owner`.
+    pub fn with_owner(self, owner: u64) -> Self {
+        match self {
+            Self::Market(market_order) => Self::Market(MarketOrder {
+                owner: Some(owner),
+                ..market_order
+            }),
+            Self::Limit(limit_order) => Self::Limit(LimitOrder {
+                owner: Some(owner),
+                ..limit_order
+            }),
+            Self::Stop(stop_order) => Self::Stop(StopOrder {
+                owner: Some(owner),
+                ..stop_order
+            }),
+            Self::Oco(oco_order) => Self::Oco(OcoOrder {
+                limit_order: LimitOrder {
+                    owner: Some(owner),
+                    ..oco_order.limit_order
+                },
+                stop_order: StopOrder {
+                    owner: Some(owner),
+                    ..oco_order.stop_order
+                },
+            }),
+            Self::Bracket(bracket_order) => Self::Bracket(BracketOrder {
+                entry_order: LimitOrder {
+                    owner: Some(owner),
+                    ..bracket_order.entry_order
+                },
+                take_profit_order: LimitOrder {
+                    owner: Some(owner),
+                    ..bracket_order.take_profit_order
+                },
+                stop_loss_order: StopOrder {
+                    owner: Some(owner),
+                    ..bracket_order.stop_loss_order
+                },
+            }),
+            request => request,
+        }
+    }
+
+    /// `true` if the request is accepted in the given session state.
+    pub fn is_allowed_in(&self, session_state: SessionState) -> bool {
+        match (session_state, self) {
+            (SessionState::Continuous, _) => true,
+            (SessionState::PreOpen | SessionState::Auction, Self::Market(_)) => false,
+            (SessionState::PreOpen | SessionState::Auction, Self::Limit(limit_order)) => {
+                limit_order.is_queueable()
+            }
+            (SessionState::PreOpen | SessionState::Auction, Self::Oco(oco_order)) => {
+                oco_order.limit_order.is_queueable()
+            }
+            (SessionState::PreOpen | SessionState::Auction, Self::Bracket(bracket_order)) => {
+                bracket_order.entry_order.is_queueable()
+            }
+            (SessionState::PreOpen | SessionState::Auction, _) => true,
+            (SessionState::Closed, Self::Cancel(_)) => true,
+            (SessionState::Closed, _) => false,
+        }
+    }
+
     pub fn new_market(
         id: ID,
         asset_pair: AssetPair<A>,
@@ -21,12 +106,128 @@ impl<ID: OrderId, A: Asset, P: Price, Q: Quantity> OrderRequest<ID, A, P, Q> {
             id,
             asset_pair,
             side,
+            owner: None,
+            quantity: MarketOrderQuantity::Base(quantity),
+            price_protection: None,
+            execution_constraint: None,
+            market_to_limit: false,
+            timestamp_ms,
+        })
+    }
+
+    pub fn new_market_quote(
+        id: ID,
+        asset_pair: AssetPair<A>,
+        side: OrderSide,
+        quote_quantity: Q,
+        timestamp_ms: u64,
+    ) -> Self {
+        Self::Market(MarketOrder {
+            id,
+            asset_pair,
+            side,
+            owner: None,
+            quantity: MarketOrderQuantity::Quote(quote_quantity),
+            price_protection: None,
+            execution_constraint: None,
+            market_to_limit: false,
+            timestamp_ms,
+        })
+    }
+
+    pub fn new_market_to_limit(
+        id: ID,
+        asset_pair: AssetPair<A>,
+        side: OrderSide,
+        quantity: Q,
+        timestamp_ms: u64,
+    ) -> Self {
+        Self::Market(MarketOrder {
+            id,
+            asset_pair,
+            side,
+            owner: None,
+            quantity: MarketOrderQuantity::Base(quantity),
+            price_protection: None,
+            execution_constraint: None,
+            market_to_limit: true,
+            timestamp_ms,
+        })
+    }
+
+    pub fn new_protected_market(
+        id: ID,
+        asset_pair: AssetPair<A>,
+        side: OrderSide,
+        quantity: MarketOrderQuantity<Q>,
+        price_protection: PriceProtection<P>,
+        timestamp_ms: u64,
+    ) -> Self {
+        Self::Market(MarketOrder {
+            id,
+            asset_pair,
+            side,
+            owner: None,
             quantity,
+            price_protection: Some(price_protection),
+            execution_constraint: None,
+            market_to_limit: false,
+            timestamp_ms,
+        })
+    }
+
+    pub fn new_constrained_market(
+        id: ID,
+        asset_pair: AssetPair<A>,
+        side: OrderSide,
+        quantity: MarketOrderQuantity<Q>,
+        execution_constraint: ExecutionConstraint<Q>,
+        timestamp_ms: u64,
+    ) -> Self {
+        Self::Market(MarketOrder {
+            id,
+            asset_pair,
+            side,
+            owner: None,
+            quantity,
+            price_protection: None,
+            execution_constraint: Some(execution_constraint),
+            market_to_limit: false,
             timestamp_ms,
         })
     }
 
     pub fn new_limit(
+        id: ID,
+        asset_pair: AssetPair<A>,
+        side: OrderSide,
+        price: P,
+        quantity: Q,
+        time_in_force: TimeInForce,
+        timestamp_ms: u64,
+    ) -> Self {
+        Self::Limit(LimitOrder {
+            time_in_force,
+            ..LimitOrder::new(id, asset_pair, side, price, quantity, timestamp_ms)
+        })
+    }
+
+    pub fn new_iceberg(
+        id: ID,
+        asset_pair: AssetPair<A>,
+        side: OrderSide,
+        price: P,
+        quantity: Q,
+        peak_quantity: Q,
+        timestamp_ms: u64,
+    ) -> Self {
+        Self::Limit(LimitOrder {
+            iceberg: Some(Iceberg::new(peak_quantity)),
+            ..LimitOrder::new(id, asset_pair, side, price, quantity, timestamp_ms)
+        })
+    }
+
+    pub fn new_hidden(
         id: ID,
         asset_pair: AssetPair<A>,
         side: OrderSide,
@@ -35,15 +236,207 @@ impl<ID: OrderId, A: Asset, P: Price, Q: Quantity> OrderRequest<ID, A, P, Q> {
         timestamp_ms: u64,
     ) -> Self {
         Self::Limit(LimitOrder {
+            hidden: true,
+            ..LimitOrder::new(id, asset_pair, side, price, quantity, timestamp_ms)
+        })
+    }
+
+    pub fn new_good_till_date(
+        id: ID,
+        asset_pair: AssetPair<A>,
+        side: OrderSide,
+        price: P,
+        quantity: Q,
+        expire_time_ms: u64,
+        timestamp_ms: u64,
+    ) -> Self {
+        Self::Limit(LimitOrder {
+            expire_time_ms: Some(expire_time_ms),
+            ..LimitOrder::new(id, asset_pair, side, price, quantity, timestamp_ms)
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_constrained_limit(
+        id: ID,
+        asset_pair: AssetPair<A>,
+        side: OrderSide,
+        price: P,
+        quantity: Q,
+        time_in_force: TimeInForce,
+        execution_constraint: ExecutionConstraint<Q>,
+        timestamp_ms: u64,
+    ) -> Self {
+        Self::Limit(LimitOrder {
+            time_in_force,
+            execution_constraint: Some(execution_constraint),
+            ..LimitOrder::new(id, asset_pair, side, price, quantity, timestamp_ms)
+        })
+    }
+
+    /// the price of the order follows the peg, never beyond the price cap if any.
+    /// the order gets its price from the order book when it is processed.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_pegged(
+        id: ID,
+        asset_pair: AssetPair<A>,
+        side: OrderSide,
+        peg_type: PegType,
+        offset: Option<P>,
+        price_cap: Option<P>,
+        quantity: Q,
+        timestamp_ms: u64,
+    ) -> Self
+    where
+        P: Default,
+    {
+        Self::Limit(LimitOrder {
+            peg: Some(Peg {
+                peg_type,
+                offset,
+                price_cap,
+            }),
+            ..LimitOrder::new(
+                id,
+                asset_pair,
+                side,
+                price_cap.unwrap_or_default(),
+                quantity,
+                timestamp_ms,
+            )
+        })
+    }
+
+    pub fn new_limit_maker(
+        id: ID,
+        asset_pair: AssetPair<A>,
+        side: OrderSide,
+        price: P,
+        quantity: Q,
+        post_only: PostOnly,
+        timestamp_ms: u64,
+    ) -> Self {
+        Self::Limit(LimitOrder {
+            post_only: Some(post_only),
+            ..LimitOrder::new(id, asset_pair, side, price, quantity, timestamp_ms)
+        })
+    }
+
+    pub fn new_stop_loss(
+        id: ID,
+        asset_pair: AssetPair<A>,
+        side: OrderSide,
+        stop_price: P,
+        quantity: Q,
+        timestamp_ms: u64,
+    ) -> Self {
+        Self::Stop(StopOrder {
             id,
             asset_pair,
+            order_type: OrderType::StopLoss,
             side,
-            price,
+            owner: None,
+            stop_price,
+            price: None,
+            quantity,
+            trailing_stop: None,
+            timestamp_ms,
+        })
+    }
+
+    pub fn new_stop_loss_limit(
+        id: ID,
+        asset_pair: AssetPair<A>,
+        side: OrderSide,
+        stop_price: P,
+        price: P,
+        quantity: Q,
+        timestamp_ms: u64,
+    ) -> Self {
+        Self::Stop(StopOrder {
+            id,
+            asset_pair,
+            order_type: OrderType::StopLossLimit,
+            side,
+            owner: None,
+            stop_price,
+            price: Some(price),
+            quantity,
+            trailing_stop: None,
+            timestamp_ms,
+        })
+    }
+
+    pub fn new_take_profit(
+        id: ID,
+        asset_pair: AssetPair<A>,
+        side: OrderSide,
+        stop_price: P,
+        quantity: Q,
+        timestamp_ms: u64,
+    ) -> Self {
+        Self::Stop(StopOrder {
+            id,
+            asset_pair,
+            order_type: OrderType::TakeProfit,
+            side,
+            owner: None,
+            stop_price,
+            price: None,
+            quantity,
+            trailing_stop: None,
+            timestamp_ms,
+        })
+    }
+
+    pub fn new_take_profit_limit(
+        id: ID,
+        asset_pair: AssetPair<A>,
+        side: OrderSide,
+        stop_price: P,
+        price: P,
+        quantity: Q,
+        timestamp_ms: u64,
+    ) -> Self {
+        Self::Stop(StopOrder {
+            id,
+            asset_pair,
+            order_type: OrderType::TakeProfitLimit,
+            side,
+            owner: None,
+            stop_price,
+            price: Some(price),
+            quantity,
+            trailing_stop: None,
+            timestamp_ms,
+        })
+    }
+
+    /// `stop_price` is the initial stop price until the trades move it.
+    pub fn new_trailing_stop(
+        id: ID,
+        asset_pair: AssetPair<A>,
+        side: OrderSide,
+        stop_price: P,
+        offset: TrailingOffset<P>,
+        quantity: Q,
+        timestamp_ms: u64,
+    ) -> Self {
+        Self::Stop(StopOrder {
+            id,
+            asset_pair,
+            order_type: OrderType::TrailingStop,
+            side,
+            owner: None,
+            stop_price,
+            price: None,
             quantity,
+            trailing_stop: Some(TrailingStop::new(offset)),
             timestamp_ms,
         })
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn new_amend(
         id: ID,
         asset_pair: AssetPair<A>,
@@ -61,11 +454,59 @@ impl<ID: OrderId, A: Asset, P: Price, Q: Quantity> OrderRequest<ID, A, P, Q> {
             target_order_type,
             side,
             price,
+            stop_price: None,
             quantity,
             timestamp_ms,
         })
     }
 
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_amend_stop(
+        id: ID,
+        asset_pair: AssetPair<A>,
+        target_id: ID,
+        target_order_type: OrderType,
+        side: OrderSide,
+        stop_price: P,
+        price: P,
+        quantity: Q,
+        timestamp_ms: u64,
+    ) -> Self {
+        Self::Amend(AmendOrder {
+            id,
+            asset_pair,
+            target_id,
+            target_order_type,
+            side,
+            price,
+            stop_price: Some(stop_price),
+            quantity,
+            timestamp_ms,
+        })
+    }
+
+    pub fn new_oco(
+        limit_order: LimitOrder<ID, A, P, Q>,
+        stop_order: StopOrder<ID, A, P, Q>,
+    ) -> Self {
+        Self::Oco(OcoOrder {
+            limit_order,
+            stop_order,
+        })
+    }
+
+    pub fn new_bracket(
+        entry_order: LimitOrder<ID, A, P, Q>,
+        take_profit_order: LimitOrder<ID, A, P, Q>,
+        stop_loss_order: StopOrder<ID, A, P, Q>,
+    ) -> Self {
+        Self::Bracket(BracketOrder {
+            entry_order,
+            take_profit_order,
+            stop_loss_order,
+        })
+    }
+
     pub fn new_cancel(
         id: ID,
         asset_pair: AssetPair<A>,
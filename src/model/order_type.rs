@@ -1,10 +1,12 @@
 #[derive(Eq, PartialEq, Debug, Copy, Clone)]
 pub enum OrderType {
     Market,
+    MarketToLimit,
     Limit,
-    // StopLoss,
-    // StopLossLimit,
-    // TakeProfit,
-    // TakeProfitLimit,
-    // LimitMaker,
+    StopLoss,
+    StopLossLimit,
+    TakeProfit,
+    TakeProfitLimit,
+    TrailingStop,
+    LimitMaker,
 }
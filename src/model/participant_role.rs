@@ -0,0 +1,8 @@
+/// role of the participant entering an order, used by the allocation of
+/// `MatchingPolicy::TopOrderProRata`.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum ParticipantRole {
+    Regular,
+    /// designated lead market maker receiving a configured share of the incoming quantity.
+    LeadMarketMaker,
+}
@@ -0,0 +1,100 @@
+use super::{checked_sub, OrderSide, Price};
+
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum PegType {
+    /// follows the best price on the same side
+    Primary,
+    /// follows the best price on the opposite side
+    Market,
+    /// follows the midpoint of the best bid and the best ask
+    Midpoint,
+}
+
+/// reference of a pegged limit order. the order price follows the reference price
+/// of the book with the offset moving it away from the opposite side.
+#[derive(Debug, Copy, Clone)]
+pub struct Peg<P: Price> {
+    pub peg_type: PegType,
+    pub offset: Option<P>,
+    /// highest price of a buy order, or lowest price of a sell order
+    pub price_cap: Option<P>,
+}
+
+impl<P: Price> Peg<P> {
+    pub fn new(peg_type: PegType) -> Self {
+        Self {
+            peg_type,
+            offset: None,
+            price_cap: None,
+        }
+    }
+
+    /// price of the pegged order at the given reference prices, `None` without a reference
+    /// or when the offset would take the price below zero. the midpoint is rounded to
+    /// `tick_size` away from the opposite side.
+    pub fn price(
+        &self,
+        side: OrderSide,
+        best_bid: Option<P>,
+        best_ask: Option<P>,
+        tick_size: P,
+    ) -> Option<P> {
+        let reference = match (self.peg_type, side) {
+            (PegType::Primary, OrderSide::Bid) | (PegType::Market, OrderSide::Ask) => best_bid?,
+            (PegType::Primary, OrderSide::Ask) | (PegType::Market, OrderSide::Bid) => best_ask?,
+            (PegType::Midpoint, _) => {
+                let (best_bid, best_ask) = (best_bid?, best_ask?);
+                let half_spread = checked_sub(best_ask, best_bid)? / 2;
+                let ticks = whole_ticks(half_spread, tick_size);
+                match side {
+                    OrderSide::Bid => best_bid + tick_size * ticks,
+                    OrderSide::Ask => best_ask - tick_size * ticks,
+                }
+            }
+        };
+        let price = match (side, self.offset) {
+            (_, None) => reference,
+            (OrderSide::Bid, Some(offset)) => checked_sub(reference, offset)?,
+            (OrderSide::Ask, Some(offset)) => reference + offset,
+        };
+        Some(match (side, self.price_cap) {
+            (_, None) => price,
+            (OrderSide::Bid, Some(price_cap)) => price.min(price_cap),
+            (OrderSide::Ask, Some(price_cap)) => price.max(price_cap),
+        })
+    }
+}
+
+/// the number of whole ticks in `span`. prices are not divisible by each other, so the
+/// number is searched by doubling and then bisecting.
+fn whole_ticks<P: Price>(span: P, tick_size: P) -> u64 {
+    assert!(tick_size * 2 > tick_size, "the tick size must be positive");
+    let mut high = 1;
+    while tick_size * high <= span {
+        high *= 2;
+    }
+    let mut low = high / 2;
+    while high - low > 1 {
+        let ticks = low + (high - low) / 2;
+        if tick_size * ticks <= span {
+            low = ticks;
+        } else {
+            high = ticks;
+        }
+    }
+    low
+}
+
+/// prices of the order book followed by the pegged orders. the pegged orders are repriced
+/// only when these prices change.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct PegReference<P: Price> {
+    /// best bid of the displayed orders that are not pegged themselves
+    pub best_bid: Option<P>,
+    /// best ask of the displayed orders that are not pegged themselves
+    pub best_ask: Option<P>,
+    /// best bid of all orders, hidden ones included, bounding the pegged sell orders
+    pub best_resting_bid: Option<P>,
+    /// best ask of all orders, hidden ones included, bounding the pegged buy orders
+    pub best_resting_ask: Option<P>,
+}
@@ -0,0 +1,8 @@
+/// behaviour of a post-only (LimitMaker) order whose price would cross the best opposite order.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum PostOnly {
+    /// reject the order.
+    Reject,
+    /// reprice the order one tick behind the best opposite order.
+    Reprice,
+}
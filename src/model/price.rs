@@ -1,5 +1,43 @@
 use core::fmt::{Debug, Display};
+use std::ops::{Add, Div, Mul, Sub};
 
-pub trait Price: PartialOrd + Ord + PartialEq + Eq + Clone + Copy + Debug + Display {}
+pub trait Price:
+    PartialOrd
+    + Ord
+    + PartialEq
+    + Eq
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<u64, Output = Self>
+    + Div<u64, Output = Self>
+    + Clone
+    + Copy
+    + Debug
+    + Display
+{
+}
 
-impl<T> Price for T where T: PartialOrd + Ord + PartialEq + Eq + Clone + Copy + Debug + Display {}
+impl<T> Price for T where
+    T: PartialOrd
+        + Ord
+        + PartialEq
+        + Eq
+        + Add<Output = Self>
+        + Sub<Output = Self>
+        + Mul<u64, Output = Self>
+        + Div<u64, Output = Self>
+        + Clone
+        + Copy
+        + Debug
+        + Display
+{
+}
+
+/// `price - sub`, or `None` if `sub` exceeds `price` as a price is never negative.
+pub(crate) fn checked_sub<P: Price>(price: P, sub: P) -> Option<P> {
+    if sub <= price {
+        Some(price - sub)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,37 @@
+use super::{OrderSide, Price};
+
+/// limits how far a market order may trade away from the price of its first fill.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum PriceProtection<P: Price> {
+    Absolute(P),
+    /// percentage offset in basis points (1/100 of a percent)
+    BasisPoints(u64),
+}
+
+impl<P: Price> PriceProtection<P> {
+    fn offset(&self, first_price: P) -> P {
+        match *self {
+            PriceProtection::Absolute(offset) => offset,
+            PriceProtection::BasisPoints(bps) => first_price * bps / 10_000,
+        }
+    }
+
+    /// whether trading at `price` would slip beyond the protection from the first matched price.
+    pub fn is_exceeded_by(&self, side: OrderSide, first_price: P, price: P) -> bool {
+        let offset = self.offset(first_price);
+        match side {
+            OrderSide::Bid => price > first_price && price - first_price > offset,
+            OrderSide::Ask => price < first_price && first_price - price > offset,
+        }
+    }
+    /// the worst price the order may trade at from the first matched price, `None` if the
+    /// protection does not bound the price of an ask.
+    pub fn limit_price(&self, side: OrderSide, first_price: P) -> Option<P> {
+        let offset = self.offset(first_price);
+        match side {
+            OrderSide::Bid => Some(first_price + offset),
+            OrderSide::Ask if offset < first_price => Some(first_price - offset),
+            OrderSide::Ask => None,
+        }
+    }
+}
@@ -1,12 +1,30 @@
 use core::fmt::{Debug, Display};
-use std::ops::Sub;
+use std::ops::{Add, Sub};
 
 pub trait Quantity:
-    PartialOrd + Ord + PartialEq + Eq + Sub<Output = Self> + Clone + Copy + Debug + Display
+    PartialOrd
+    + Ord
+    + PartialEq
+    + Eq
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Clone
+    + Copy
+    + Debug
+    + Display
 {
 }
 
 impl<T> Quantity for T where
-    T: PartialOrd + Ord + PartialEq + Eq + Sub<Output = Self> + Clone + Copy + Debug + Display
+    T: PartialOrd
+        + Ord
+        + PartialEq
+        + Eq
+        + Add<Output = Self>
+        + Sub<Output = Self>
+        + Clone
+        + Copy
+        + Debug
+        + Display
 {
 }
@@ -0,0 +1,12 @@
+/// action taken when an incoming order would trade with a resting order of the same owner.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum SelfTradePrevention {
+    /// cancel the incoming order. the resting order stays in the book.
+    CancelNewest,
+    /// cancel the resting order. the incoming order continues matching.
+    CancelOldest,
+    /// cancel both orders.
+    CancelBoth,
+    /// decrement both orders by the smaller quantity and cancel the order left without quantity.
+    DecrementAndCancel,
+}
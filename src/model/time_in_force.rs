@@ -0,0 +1,11 @@
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum TimeInForce {
+    /// Good Till Cancel: the unmatched part rests in the order book.
+    GTC,
+    /// Immediate Or Cancel: the unmatched part is expired instead of resting.
+    IOC,
+    /// Fill Or Kill: the order is expired without matching unless it could be completely filled.
+    FOK,
+    /// Day: the unmatched part rests in the order book until the close of the trading session.
+    DAY,
+}
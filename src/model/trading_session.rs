@@ -0,0 +1,29 @@
+/// trading state of the instrument.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum SessionState {
+    /// call phase of the opening auction. limit orders are collected in the order book
+    /// without matching until the uncross.
+    PreOpen,
+    /// orders are matched as they arrive.
+    Continuous,
+    /// call phase of a closing auction, collecting orders like `PreOpen`.
+    Auction,
+    /// only cancels are accepted.
+    Closed,
+}
+
+impl SessionState {
+    /// `true` during the call phase of an auction.
+    pub fn collects_orders(&self) -> bool {
+        matches!(self, Self::PreOpen | Self::Auction)
+    }
+}
+
+/// schedule of the trading sessions of the instrument.
+pub trait TradingScheduleLike: Send {
+    fn session_state(&self, timestamp_ms: u64) -> SessionState;
+
+    /// close time of the session in progress or the next session at `timestamp_ms`.
+    /// `None` if the session never closes.
+    fn session_close_time_ms(&self, timestamp_ms: u64) -> Option<u64>;
+}
@@ -0,0 +1,45 @@
+use super::{OrderSide, Price};
+
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum TrailingOffset<P: Price> {
+    Absolute(P),
+    /// percentage offset in basis points (1/100 of a percent)
+    BasisPoints(u64),
+}
+
+/// state of a trailing stop order. the stop price follows the highest trade price
+/// for sell orders and the lowest trade price for buy orders.
+#[derive(Debug, Copy, Clone)]
+pub struct TrailingStop<P: Price> {
+    pub offset: TrailingOffset<P>,
+    /// highest (sell) or lowest (buy) trade price since the order was placed
+    pub water_mark: Option<P>,
+}
+
+impl<P: Price> TrailingStop<P> {
+    pub fn new(offset: TrailingOffset<P>) -> Self {
+        Self {
+            offset,
+            water_mark: None,
+        }
+    }
+
+    /// updates the water mark with the range of trade prices and returns the stop price it implies.
+    pub fn trail(&mut self, side: OrderSide, low: P, high: P) -> P {
+        let water_mark = match (side, self.water_mark) {
+            (OrderSide::Ask, Some(water_mark)) => water_mark.max(high),
+            (OrderSide::Ask, None) => high,
+            (OrderSide::Bid, Some(water_mark)) => water_mark.min(low),
+            (OrderSide::Bid, None) => low,
+        };
+        self.water_mark = Some(water_mark);
+        let offset = match self.offset {
+            TrailingOffset::Absolute(offset) => offset,
+            TrailingOffset::BasisPoints(bps) => water_mark * bps / 10_000,
+        };
+        match side {
+            OrderSide::Ask => water_mark - offset,
+            OrderSide::Bid => water_mark + offset,
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use crate::{Asset, ChildOrders, OrderId, Price, Quantity};
+
+pub trait ChildOrderRepositoryLike: Send {
+    type Err;
+    type Asset: Asset;
+    type OrderId: OrderId;
+    type Price: Price;
+    type Quantity: Quantity;
+    type Transaction;
+
+    fn create(
+        &self,
+        tx: &mut Self::Transaction,
+        child_orders: &ChildOrders<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+    ) -> Result<(), Self::Err>;
+
+    fn update(
+        &self,
+        tx: &mut Self::Transaction,
+        child_orders: &ChildOrders<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+    ) -> Result<(), Self::Err>;
+
+    fn delete_by_parent_id(
+        &self,
+        tx: &mut Self::Transaction,
+        parent_id: &Self::OrderId,
+    ) -> Result<(), Self::Err>;
+
+    fn get_by_parent_id(
+        &self,
+        tx: &mut Self::Transaction,
+        parent_id: &Self::OrderId,
+    ) -> Result<Option<ChildOrdersOf<Self>>, Self::Err>;
+}
+
+/// the child orders stored by the repository `R`.
+pub type ChildOrdersOf<R> = ChildOrders<
+    <R as ChildOrderRepositoryLike>::OrderId,
+    <R as ChildOrderRepositoryLike>::Asset,
+    <R as ChildOrderRepositoryLike>::Price,
+    <R as ChildOrderRepositoryLike>::Quantity,
+>;
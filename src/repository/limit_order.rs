@@ -14,6 +14,8 @@ pub trait LimitOrderRepositoryLike: Send {
         order: &LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
     ) -> Result<(), Self::Err>;
 
+    /// updates the stored order. an order whose `timestamp_ms` has changed loses its time priority
+    /// and moves to the end of its price level.
     fn update(
         &self,
         tx: &mut Self::Transaction,
@@ -30,16 +32,30 @@ pub trait LimitOrderRepositoryLike: Send {
         &self,
         tx: &mut Self::Transaction,
         order_id: &Self::OrderId,
-    ) -> Result<
-        Option<LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>>,
-        Self::Err,
-    >;
+    ) -> Result<Option<LimitOrderOf<Self>>, Self::Err>;
 
-    fn next(
+    /// returns the order with the highest priority: the best price first, then displayed orders
+    /// before hidden orders (see `LimitOrder::hidden`) at the same price, then the oldest order.
+    fn next(&self, tx: &mut Self::Transaction) -> Result<Option<LimitOrderOf<Self>>, Self::Err>;
+
+    /// returns the order which follows the given order in the same priority as `next`.
+    fn next_after(
+        &self,
+        tx: &mut Self::Transaction,
+        order: &LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+    ) -> Result<Option<LimitOrderOf<Self>>, Self::Err>;
+
+    /// returns the stored pegged orders (see `LimitOrder::peg`).
+    fn get_pegged_orders(
         &self,
         tx: &mut Self::Transaction,
-    ) -> Result<
-        Option<LimitOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>>,
-        Self::Err,
-    >;
+    ) -> Result<Vec<LimitOrderOf<Self>>, Self::Err>;
 }
+
+/// the limit order stored by the repository `R`.
+pub type LimitOrderOf<R> = LimitOrder<
+    <R as LimitOrderRepositoryLike>::OrderId,
+    <R as LimitOrderRepositoryLike>::Asset,
+    <R as LimitOrderRepositoryLike>::Price,
+    <R as LimitOrderRepositoryLike>::Quantity,
+>;
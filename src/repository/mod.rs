@@ -1,3 +1,9 @@
+mod child_order;
 mod limit_order;
+mod order_link;
+mod stop_order;
 
+pub use child_order::*;
 pub use limit_order::*;
+pub use order_link::*;
+pub use stop_order::*;
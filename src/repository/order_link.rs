@@ -0,0 +1,25 @@
+use crate::{OrderId, OrderLink};
+
+pub trait OrderLinkRepositoryLike: Send {
+    type Err;
+    type OrderId: OrderId;
+    type Transaction;
+
+    fn create(
+        &self,
+        tx: &mut Self::Transaction,
+        link: &OrderLink<Self::OrderId>,
+    ) -> Result<(), Self::Err>;
+
+    fn delete_by_order_id(
+        &self,
+        tx: &mut Self::Transaction,
+        order_id: &Self::OrderId,
+    ) -> Result<(), Self::Err>;
+
+    fn get_by_order_id(
+        &self,
+        tx: &mut Self::Transaction,
+        order_id: &Self::OrderId,
+    ) -> Result<Option<OrderLink<Self::OrderId>>, Self::Err>;
+}
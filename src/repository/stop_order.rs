@@ -0,0 +1,55 @@
+use crate::{Asset, OrderId, Price, Quantity, StopOrder};
+
+pub trait StopOrderRepositoryLike: Send {
+    type Err;
+    type Asset: Asset;
+    type OrderId: OrderId;
+    type Price: Price;
+    type Quantity: Quantity;
+    type Transaction;
+
+    fn create(
+        &self,
+        tx: &mut Self::Transaction,
+        order: &StopOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+    ) -> Result<(), Self::Err>;
+
+    fn update(
+        &self,
+        tx: &mut Self::Transaction,
+        order: &StopOrder<Self::OrderId, Self::Asset, Self::Price, Self::Quantity>,
+    ) -> Result<(), Self::Err>;
+
+    fn delete_by_order_id(
+        &self,
+        tx: &mut Self::Transaction,
+        order_id: &Self::OrderId,
+    ) -> Result<(), Self::Err>;
+
+    fn get_by_order_id(
+        &self,
+        tx: &mut Self::Transaction,
+        order_id: &Self::OrderId,
+    ) -> Result<Option<StopOrderOf<Self>>, Self::Err>;
+
+    /// returns one of the stored orders triggered by the given last trade price
+    /// (see `StopOrder::is_triggered_by`).
+    fn next_triggered(
+        &self,
+        tx: &mut Self::Transaction,
+        last_price: &Self::Price,
+    ) -> Result<Option<StopOrderOf<Self>>, Self::Err>;
+
+    fn get_trailing_stop_orders(
+        &self,
+        tx: &mut Self::Transaction,
+    ) -> Result<Vec<StopOrderOf<Self>>, Self::Err>;
+}
+
+/// the stop order stored by the repository `R`.
+pub type StopOrderOf<R> = StopOrder<
+    <R as StopOrderRepositoryLike>::OrderId,
+    <R as StopOrderRepositoryLike>::Asset,
+    <R as StopOrderRepositoryLike>::Price,
+    <R as StopOrderRepositoryLike>::Quantity,
+>;